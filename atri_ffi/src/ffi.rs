@@ -2,7 +2,7 @@ use std::future::Future;
 use std::marker::PhantomData;
 
 use crate::future::FFIFuture;
-use crate::Managed;
+use crate::{Managed, RawString, RawVec};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use crate::closure::FFIFn;
@@ -15,6 +15,8 @@ pub struct AtriVTable {
     extern "C" fn(manager: *const (), FFIFuture<Managed>) -> Managed,
     pub new_listener:
     extern "C" fn(FFIFn<FFIFuture<bool>, FFIEvent>) -> Managed,
+    pub new_command:
+    extern "C" fn(RawString, RawString, RawString, FFIFn<FFIFuture<()>, RawVec<RawString>>) -> Managed,
 }
 
 #[repr(C)]
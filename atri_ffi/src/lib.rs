@@ -112,3 +112,31 @@ impl From<String> for RawString {
         }
     }
 }
+
+#[repr(C)]
+pub struct RawVec<T> {
+    pointer: *mut T,
+    length: usize,
+    capacity: usize,
+}
+
+impl<T> RawVec<T> {
+    pub fn into_vec(self) -> Vec<T> {
+        unsafe { Vec::from_raw_parts(self.pointer, self.length, self.capacity) }
+    }
+}
+
+impl<T> From<Vec<T>> for RawVec<T> {
+    fn from(v: Vec<T>) -> Self {
+        let mut ma = ManuallyDrop::new(v);
+        let ptr = ma.as_mut_ptr();
+        let len = ma.len();
+        let cap = ma.capacity();
+
+        Self {
+            pointer: ptr,
+            length: len,
+            capacity: cap,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::future::Future;
+use atri_ffi::closure::FFIFn;
+use atri_ffi::future::FFIFuture;
+use atri_ffi::{Managed, RawString, RawVec};
+use crate::loader::get_plugin_manager_vtb;
+
+pub struct Command;
+
+impl Command {
+    /// 注册控制台指令，参数已按空白及引号分割，不包含指令名
+    pub fn register<F, Fu>(name: &str, usage: &str, description: &str, handler: F) -> CommandGuard
+        where
+            F: Fn(Vec<String>) -> Fu,
+            F: Send + Sync + 'static,
+            Fu: Future<Output = ()>,
+            Fu: Send + 'static,
+    {
+        let f = FFIFn::from(move |args: RawVec<RawString>| {
+            let args = args
+                .into_vec()
+                .into_iter()
+                .filter_map(RawString::to_string)
+                .collect();
+            FFIFuture::from(handler(args))
+        });
+        let ma = (get_plugin_manager_vtb().new_command)(
+            RawString::from(name.to_string()),
+            RawString::from(usage.to_string()),
+            RawString::from(description.to_string()),
+            f,
+        );
+        CommandGuard(ma)
+    }
+}
+
+pub struct CommandGuard(Managed);
@@ -1,6 +1,7 @@
 use atri_ffi::plugin::{PluginVTable};
 use atri_ffi::Managed;
 
+pub mod command;
 pub mod loader;
 pub mod manager;
 pub mod listener;
@@ -1,18 +0,0 @@
-static ANDROID_PHONE: &Version = &Version {
-    apk_id: "com.tencent.mobileqq",
-    app_id: 537113159,
-    sub_app_id: 537113159,
-    sort_version_name: "8.8.80",
-    build_ver: "8.8.80.7400",
-    build_time: 1640921786,
-    apk_sign: &[
-        0xA6, 0xB7, 0x45, 0xBF, 0x24, 0xA2, 0xC2, 0x77, 0x52, 0x77, 0x16, 0xF6, 0xF3, 0x6E, 0xB6,
-        0x8D,
-    ],
-    sdk_version: "6.0.0.2494",
-    sso_version: 16,
-    misc_bitmap: 184024956,
-    sub_sig_map: 0x10400,
-    main_sig_map: 34869472,
-    protocol: Protocol::AndroidPhone,
-};
\ No newline at end of file
@@ -0,0 +1,532 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use ricq::device::Device;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::fs;
+use tracing::{error, warn};
+
+pub fn device_file_buf(work_dir: &Path) -> PathBuf {
+    work_dir.join("device.json")
+}
+
+/// 生成随机设备信息, 指定种子时生成的设备信息固定
+pub fn random_device(seed: Option<u64>) -> Device {
+    match seed {
+        Some(seed) => Device::random_with_rng(&mut StdRng::seed_from_u64(seed)),
+        None => Device::random(),
+    }
+}
+
+pub async fn write_device(work_dir: &Path, device: &Device) -> io::Result<()> {
+    if !work_dir.is_dir() {
+        fs::create_dir_all(work_dir).await?;
+    }
+
+    let s = serde_json::to_string_pretty(device).expect("Cannot serialize device info");
+    fs::write(device_file_buf(work_dir), s).await
+}
+
+pub async fn read_device(work_dir: &Path) -> Result<Device, DeviceError> {
+    let s = fs::read_to_string(device_file_buf(work_dir)).await?;
+    Ok(serde_json::from_str(&s)?)
+}
+
+/// 读取设备信息, 不存在或损坏时生成新的设备信息并写入
+pub(crate) async fn load_device(id: i64, work_dir: &Path) -> Device {
+    let path = device_file_buf(work_dir);
+
+    if !path.is_file() {
+        warn!(
+            "未找到Bot({})的设备信息, 已生成新的设备信息({}); \
+            若已有其他框架的设备文件, 可使用'device import {} <file>'导入以避免账号风控",
+            id,
+            path.display(),
+            id
+        );
+
+        let device = Device::random();
+        if let Err(e) = write_device(work_dir, &device).await {
+            error!("写入Bot({})的设备信息失败: {}", id, e);
+        }
+        return device;
+    }
+
+    match read_device(work_dir).await {
+        Ok(device) => device,
+        Err(e) => {
+            let mut bak = path.as_os_str().to_owned();
+            bak.push(".bak");
+            let bak = PathBuf::from(bak);
+
+            error!(
+                "读取Bot({})的设备信息失败: {}, 原文件已备份至{}, 将生成新的设备信息; \
+                若该文件来自其他框架, 可使用'device import {} {}'转换",
+                id,
+                e,
+                bak.display(),
+                id,
+                bak.display()
+            );
+
+            let device = Device::random();
+            if fs::copy(&path, &bak).await.is_ok() {
+                if let Err(e) = write_device(work_dir, &device).await {
+                    error!("写入Bot({})的设备信息失败: {}", id, e);
+                }
+            }
+            device
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DeviceError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    UnknownFormat,
+}
+
+impl Display for DeviceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Json(e) => write!(f, "{}", e),
+            Self::UnknownFormat => write!(f, "Unknown device format"),
+        }
+    }
+}
+
+impl Error for DeviceError {}
+
+impl From<io::Error> for DeviceError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for DeviceError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceFormat {
+    Ricq,
+    Mirai,
+    GoCqhttp,
+}
+
+impl Display for DeviceFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ricq => f.write_str("ricq"),
+            Self::Mirai => f.write_str("mirai"),
+            Self::GoCqhttp => f.write_str("go-cqhttp"),
+        }
+    }
+}
+
+/// 转换其他框架的设备信息, 缺失的字段使用随机值
+pub fn convert_device(s: &str) -> Result<(Device, DeviceFormat), DeviceError> {
+    if let Ok(device) = serde_json::from_str::<Device>(s) {
+        return Ok((device, DeviceFormat::Ricq));
+    }
+
+    let value: Value = serde_json::from_str(s)?;
+
+    // mirai: {"deviceInfoVersion": 2, "data": {...}}, 旧版本无版本号且字段为字节数组
+    if value.get("deviceInfoVersion").is_some() || value.get("fingerprint").is_some() {
+        let data = value.get("data").unwrap_or(&value);
+        let device: MiraiDevice = serde_json::from_value(data.clone())?;
+        return Ok((device.into_device(), DeviceFormat::Mirai));
+    }
+
+    if value.get("finger_print").is_some() {
+        let device: GoCqhttpDevice = serde_json::from_value(value)?;
+        return Ok((device.into_device(), DeviceFormat::GoCqhttp));
+    }
+
+    Err(DeviceError::UnknownFormat)
+}
+
+/// 兼容字符串与字节数组
+#[derive(Deserialize, Default)]
+#[serde(untagged)]
+enum Text {
+    String(String),
+    Bytes(Vec<u8>),
+    #[default]
+    #[serde(skip)]
+    None,
+}
+
+impl Text {
+    fn set(self, field: &mut String) {
+        match self {
+            Self::String(s) => *field = s,
+            Self::Bytes(b) => *field = String::from_utf8_lossy(&b).into_owned(),
+            Self::None => {}
+        }
+    }
+
+    fn set_hex(self, field: &mut Vec<u8>) {
+        match self {
+            Self::String(s) => {
+                if let Some(b) = decode_hex(&s) {
+                    *field = b;
+                }
+            }
+            Self::Bytes(b) => *field = b,
+            Self::None => {}
+        }
+    }
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 || !s.is_ascii() {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct OsVersion {
+    incremental: Text,
+    release: Text,
+    codename: Text,
+    sdk: Option<u32>,
+}
+
+impl OsVersion {
+    fn set(self, device: &mut Device) {
+        self.incremental.set(&mut device.version.incremental);
+        self.release.set(&mut device.version.release);
+        self.codename.set(&mut device.version.codename);
+        if let Some(sdk) = self.sdk {
+            device.version.sdk = sdk;
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct MiraiDevice {
+    display: Text,
+    product: Text,
+    device: Text,
+    board: Text,
+    brand: Text,
+    model: Text,
+    bootloader: Text,
+    fingerprint: Text,
+    boot_id: Text,
+    proc_version: Text,
+    base_band: Text,
+    version: OsVersion,
+    sim_info: Text,
+    os_type: Text,
+    mac_address: Text,
+    #[serde(rename = "wifiBSSID")]
+    wifi_bssid: Text,
+    #[serde(rename = "wifiSSID")]
+    wifi_ssid: Text,
+    imsi_md5: Text,
+    imei: Text,
+    apn: Text,
+    android_id: Text,
+}
+
+impl MiraiDevice {
+    fn into_device(self) -> Device {
+        let mut d = Device::random();
+        self.display.set(&mut d.display);
+        self.product.set(&mut d.product);
+        self.device.set(&mut d.device);
+        self.board.set(&mut d.board);
+        self.brand.set(&mut d.brand);
+        self.model.set(&mut d.model);
+        self.bootloader.set(&mut d.bootloader);
+        self.fingerprint.set(&mut d.finger_print);
+        self.boot_id.set(&mut d.boot_id);
+        self.proc_version.set(&mut d.proc_version);
+        self.base_band.set(&mut d.base_band);
+        self.version.set(&mut d);
+        self.sim_info.set(&mut d.sim_info);
+        self.os_type.set(&mut d.os_type);
+        self.mac_address.set(&mut d.mac_address);
+        self.wifi_bssid.set(&mut d.wifi_bssid);
+        self.wifi_ssid.set(&mut d.wifi_ssid);
+        self.imsi_md5.set_hex(&mut d.imsi_md5);
+        self.imei.set(&mut d.imei);
+        self.apn.set(&mut d.apn);
+        self.android_id.set(&mut d.android_id);
+        d
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct GoCqhttpDevice {
+    display: Text,
+    product: Text,
+    device: Text,
+    board: Text,
+    model: Text,
+    finger_print: Text,
+    boot_id: Text,
+    proc_version: Text,
+    imei: Text,
+    brand: Text,
+    bootloader: Text,
+    base_band: Text,
+    version: OsVersion,
+    sim_info: Text,
+    os_type: Text,
+    mac_address: Text,
+    ip_address: Option<Vec<u8>>,
+    wifi_bssid: Text,
+    wifi_ssid: Text,
+    imsi_md5: Text,
+    android_id: Text,
+    apn: Text,
+    vendor_name: Text,
+    vendor_os_name: Text,
+}
+
+impl GoCqhttpDevice {
+    fn into_device(self) -> Device {
+        let mut d = Device::random();
+        self.display.set(&mut d.display);
+        self.product.set(&mut d.product);
+        self.device.set(&mut d.device);
+        self.board.set(&mut d.board);
+        self.model.set(&mut d.model);
+        self.finger_print.set(&mut d.finger_print);
+        self.boot_id.set(&mut d.boot_id);
+        self.proc_version.set(&mut d.proc_version);
+        self.imei.set(&mut d.imei);
+        self.brand.set(&mut d.brand);
+        self.bootloader.set(&mut d.bootloader);
+        self.base_band.set(&mut d.base_band);
+        self.version.set(&mut d);
+        self.sim_info.set(&mut d.sim_info);
+        self.os_type.set(&mut d.os_type);
+        self.mac_address.set(&mut d.mac_address);
+        if let Some(ip) = self.ip_address {
+            d.ip_address = ip;
+        }
+        self.wifi_bssid.set(&mut d.wifi_bssid);
+        self.wifi_ssid.set(&mut d.wifi_ssid);
+        self.imsi_md5.set_hex(&mut d.imsi_md5);
+        self.android_id.set(&mut d.android_id);
+        self.apn.set(&mut d.apn);
+        self.vendor_name.set(&mut d.vendor_name);
+        self.vendor_os_name.set(&mut d.vendor_os_name);
+        d
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIRAI: &str = r#"{
+    "deviceInfoVersion": 2,
+    "data": {
+        "display": "MIRAI.328126.001",
+        "product": "mirai",
+        "device": "mirai",
+        "board": "mirai",
+        "brand": "mamoe",
+        "model": "mirai",
+        "bootloader": "unknown",
+        "fingerprint": "mamoe/mirai/mirai:10/MIRAI.200122.001/2736748:user/release-keys",
+        "bootId": "8dc4ad0e-7e34-b0b3-0c2f-9fcd1e74b5e8",
+        "procVersion": "Linux version 3.0.31-hBV0tn7U (android-build@xxx.xxx.xxx.xxx.com)",
+        "baseBand": "",
+        "version": {
+            "incremental": "5891938",
+            "release": "10",
+            "codename": "REL",
+            "sdk": 29
+        },
+        "simInfo": "T-Mobile",
+        "osType": "android",
+        "macAddress": "02:00:00:00:00:00",
+        "wifiBSSID": "02:00:00:00:00:00",
+        "wifiSSID": "<unknown ssid>",
+        "imsiMd5": "e10adc3949ba59abbe56e057f20f883e",
+        "imei": "865931034526198",
+        "apn": "wifi",
+        "androidId": "MIRAI.328126.001"
+    }
+}"#;
+
+    /// 旧版mirai的设备文件, 字段为字节数组
+    const MIRAI_V1: &str = r#"{
+    "display": [77, 73, 82, 65, 73],
+    "product": [109, 105, 114, 97, 105],
+    "fingerprint": [109, 97, 109, 111, 101],
+    "version": {
+        "incremental": [53, 56, 57],
+        "release": [49, 48],
+        "codename": [82, 69, 76],
+        "sdk": 29
+    },
+    "imsiMd5": [225, 10, 220, 57, 73, 186, 89, 171, 190, 86, 224, 87, 242, 15, 136, 62],
+    "imei": "865931034526198"
+}"#;
+
+    const GO_CQHTTP: &str = r#"{
+    "protocol": 5,
+    "display": "GMC.251031.001",
+    "product": "gmc",
+    "device": "gmc",
+    "board": "gmc",
+    "model": "gmc",
+    "finger_print": "xiaomi/gmc/gmc:10/GMC.200122.001/1123456:user/release-keys",
+    "boot_id": "11b2a5b8-6e2b-4c9d-8a8e-7a5e2f2c1d3e",
+    "proc_version": "Linux version 3.0.31-WqfD6Dte (android-build@xxx.xxx.xxx.xxx.com)",
+    "imei": "864931034526197",
+    "brand": "Xiaomi",
+    "bootloader": "U-boot",
+    "base_band": "",
+    "version": {
+        "incremental": "5891938",
+        "release": "10",
+        "codename": "REL",
+        "sdk": 29
+    },
+    "sim_info": "T-Mobile",
+    "os_type": "android",
+    "mac_address": "00:50:56:C0:00:08",
+    "ip_address": [10, 0, 1, 3],
+    "wifi_bssid": "00:50:56:C0:00:08",
+    "wifi_ssid": "<unknown ssid>",
+    "imsi_md5": "e10adc3949ba59abbe56e057f20f883e",
+    "android_id": "fd3a8c0e2b1d4f57",
+    "apn": "wifi",
+    "vendor_name": "MIUI",
+    "vendor_os_name": "gmc"
+}"#;
+
+    const IMSI_MD5: [u8; 16] = [
+        0xe1, 0x0a, 0xdc, 0x39, 0x49, 0xba, 0x59, 0xab, 0xbe, 0x56, 0xe0, 0x57, 0xf2, 0x0f, 0x88,
+        0x3e,
+    ];
+
+    /// 转换后的设备信息按ricq格式写入后再次读取, 应识别为ricq格式且内容不变
+    fn assert_round_trip(device: &Device) {
+        let s = serde_json::to_string_pretty(device).unwrap();
+        let (again, format) = convert_device(&s).unwrap();
+
+        assert_eq!(format, DeviceFormat::Ricq);
+        assert_eq!(
+            serde_json::to_value(&again).unwrap(),
+            serde_json::to_value(device).unwrap()
+        );
+    }
+
+    #[test]
+    fn convert_mirai() {
+        let (d, format) = convert_device(MIRAI).unwrap();
+        assert_eq!(format, DeviceFormat::Mirai);
+
+        assert_eq!(d.display, "MIRAI.328126.001");
+        assert_eq!(d.product, "mirai");
+        assert_eq!(d.brand, "mamoe");
+        assert_eq!(
+            d.finger_print,
+            "mamoe/mirai/mirai:10/MIRAI.200122.001/2736748:user/release-keys"
+        );
+        assert_eq!(d.boot_id, "8dc4ad0e-7e34-b0b3-0c2f-9fcd1e74b5e8");
+        assert_eq!(d.base_band, "");
+        assert_eq!(d.version.incremental, "5891938");
+        assert_eq!(d.version.release, "10");
+        assert_eq!(d.version.codename, "REL");
+        assert_eq!(d.version.sdk, 29);
+        assert_eq!(d.wifi_bssid, "02:00:00:00:00:00");
+        assert_eq!(d.wifi_ssid, "<unknown ssid>");
+        assert_eq!(d.imsi_md5, IMSI_MD5);
+        assert_eq!(d.imei, "865931034526198");
+        assert_eq!(d.android_id, "MIRAI.328126.001");
+
+        assert_round_trip(&d);
+    }
+
+    #[test]
+    fn convert_mirai_v1() {
+        let (d, format) = convert_device(MIRAI_V1).unwrap();
+        assert_eq!(format, DeviceFormat::Mirai);
+
+        assert_eq!(d.display, "MIRAI");
+        assert_eq!(d.product, "mirai");
+        assert_eq!(d.finger_print, "mamoe");
+        assert_eq!(d.version.incremental, "589");
+        assert_eq!(d.version.release, "10");
+        assert_eq!(d.version.codename, "REL");
+        assert_eq!(d.imsi_md5, IMSI_MD5);
+        assert_eq!(d.imei, "865931034526198");
+        // 缺失的字段使用随机值
+        assert!(!d.android_id.is_empty());
+
+        assert_round_trip(&d);
+    }
+
+    #[test]
+    fn convert_go_cqhttp() {
+        let (d, format) = convert_device(GO_CQHTTP).unwrap();
+        assert_eq!(format, DeviceFormat::GoCqhttp);
+
+        assert_eq!(d.display, "GMC.251031.001");
+        assert_eq!(d.model, "gmc");
+        assert_eq!(
+            d.finger_print,
+            "xiaomi/gmc/gmc:10/GMC.200122.001/1123456:user/release-keys"
+        );
+        assert_eq!(d.imei, "864931034526197");
+        assert_eq!(d.brand, "Xiaomi");
+        assert_eq!(d.bootloader, "U-boot");
+        assert_eq!(d.version.sdk, 29);
+        assert_eq!(d.mac_address, "00:50:56:C0:00:08");
+        assert_eq!(d.ip_address, [10, 0, 1, 3]);
+        assert_eq!(d.imsi_md5, IMSI_MD5);
+        assert_eq!(d.android_id, "fd3a8c0e2b1d4f57");
+        assert_eq!(d.vendor_name, "MIUI");
+        assert_eq!(d.vendor_os_name, "gmc");
+
+        assert_round_trip(&d);
+    }
+
+    #[test]
+    fn convert_ricq() {
+        let device = random_device(Some(114514));
+        assert_eq!(
+            serde_json::to_value(random_device(Some(114514))).unwrap(),
+            serde_json::to_value(&device).unwrap()
+        );
+
+        assert_round_trip(&device);
+    }
+
+    #[test]
+    fn convert_unknown() {
+        assert!(matches!(
+            convert_device(r#"{"foo": 1}"#),
+            Err(DeviceError::UnknownFormat)
+        ));
+        assert!(matches!(convert_device("{"), Err(DeviceError::Json(_))));
+    }
+}
@@ -1,17 +1,30 @@
 use std::fmt::{Debug, Display, Formatter};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
-use ricq::client::Token;
+use ricq::client::{NetworkStatus, Token};
 use ricq::ext::common::after_login;
 use ricq::structs::AccountInfo;
 use ricq::{Client, LoginResponse, RQError, RQResult};
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::task::yield_now;
 use tokio::{fs, io};
-use tracing::error;
+use tracing::{error, warn};
 
+use crate::bot::status::{BotState, BotStatus};
+use crate::contact::friend::Friend;
 use crate::contact::group::Group;
+use crate::{get_app, get_runtime};
+
+pub mod device;
+pub mod proxy;
+pub mod server;
+pub mod status;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
 
 #[derive(Clone)]
 pub struct Bot(Arc<imp::Bot>);
@@ -39,13 +52,7 @@ impl Bot {
             let resp = self.0.client.token_login(token).await?;
 
             if let LoginResponse::Success(..) = resp {
-                after_login(&self.0.client).await;
-                let client = self.0.client.clone();
-                tokio::spawn(async move {
-                    client.do_heartbeat().await;
-                });
-
-                self.0.enable.swap(true, Ordering::Relaxed);
+                self.on_login_success().await;
             } else {
                 error!("Bot({})登陆失败: {:?}", self.0.client.uin().await, resp);
 
@@ -58,14 +65,116 @@ impl Bot {
         Ok(())
     }
 
+    pub(crate) async fn on_login_success(&self) {
+        after_login(&self.0.client).await;
+        self.set_online(true);
+
+        let bot = self.clone();
+        get_runtime().spawn(async move {
+            bot.keep_alive().await;
+        });
+    }
+
+    /// 运行ricq的心跳, 心跳停止(连接断开或注册客户端失败)后Bot离线;
+    /// 心跳运行且连接在线时按心跳间隔记录心跳时间
+    async fn keep_alive(&self) {
+        let client = self.client();
+        let heartbeat = client.do_heartbeat();
+        tokio::pin!(heartbeat);
+
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = &mut heartbeat => break,
+                _ = interval.tick() => {
+                    if client.online.load(Ordering::SeqCst) {
+                        self.0.status.record_heartbeat();
+                    }
+                }
+            }
+        }
+
+        self.on_disconnected("心跳已停止");
+    }
+
+    /// 连接断开或心跳停止时调用, 仅对在线的Bot生效
+    fn on_disconnected(&self, reason: &str) {
+        if !self.is_online() {
+            return;
+        }
+
+        warn!("{}{}, 已离线", self, reason);
+        self.set_online(false);
+        get_app().on_bot_offline(self.id());
+    }
+
+    pub(crate) async fn save_token(&self) {
+        let mut dir = self.work_dir();
+        dir.push("token.json");
+
+        if let Ok(mut f) = fs::File::create(&dir).await {
+            let token = self.client().gen_token().await;
+            let s = serde_json::to_string_pretty(&token).expect("Cannot serialize token");
+            let _ = f.write_all(s.as_bytes()).await;
+        }
+    }
+
     pub async fn start(&self) -> io::Result<()> {
-        self.0.start().await
+        let stream = self.0.connect().await?;
+
+        let bot = self.clone();
+        get_runtime().spawn(async move {
+            bot.client().start(stream).await;
+            bot.on_disconnected("与服务器的连接已断开");
+        });
+        yield_now().await;
+
+        Ok(())
+    }
+
+    /// 当前连接的服务器
+    pub fn server(&self) -> Option<SocketAddr> {
+        *self.0.server.read().expect("Cannot read server")
+    }
+
+    pub async fn logout(&self) {
+        // 先设置离线, 连接断开时不再视为意外离线
+        self.set_online(false);
+        self.0.client.stop(NetworkStatus::Stop);
     }
 
     pub fn id(&self) -> i64 {
         self.0.id
     }
 
+    pub fn is_online(&self) -> bool {
+        self.0.status.state() == BotState::Online
+    }
+
+    pub(crate) fn set_online(&self, online: bool) {
+        self.set_state(if online {
+            BotState::Online
+        } else {
+            BotState::Offline
+        });
+    }
+
+    pub(crate) fn set_state(&self, state: BotState) {
+        self.0.status.set_state(state);
+    }
+
+    pub fn status(&self) -> BotStatus {
+        self.0.status.snapshot(self.id(), self.server())
+    }
+
+    pub(crate) fn record_sent(&self) {
+        self.0.status.record_sent();
+    }
+
+    pub(crate) fn record_received(&self) {
+        self.0.status.record_received();
+    }
+
     pub async fn nickname(&self) -> String {
         self.0.nickname().await
     }
@@ -101,10 +210,53 @@ impl Bot {
         Ok(())
     }
 
+    pub fn has_group(&self, group_id: i64) -> bool {
+        self.0.group_list.contains_key(&group_id)
+    }
+
+    pub fn groups(&self) -> Vec<Group> {
+        self.0.group_list.iter().map(|g| g.clone()).collect()
+    }
+
     pub fn delete_group(&self, group_id: i64) -> Option<Group> {
         self.0.group_list.remove(&group_id).map(|(_, g)| g)
     }
 
+    pub async fn refresh_friend_list(&self) -> RQResult<()> {
+        let resp = self.client().get_friend_list().await?;
+        self.0.friend_list.clear();
+        for info in resp.friends {
+            let id = info.uin;
+            self.0
+                .friend_list
+                .insert(id, Friend::from(self.clone(), info));
+        }
+
+        Ok(())
+    }
+
+    pub fn friends(&self) -> Vec<Friend> {
+        self.0.friend_list.iter().map(|f| f.clone()).collect()
+    }
+
+    /// 只在已缓存的好友列表中查找
+    pub fn cached_friend(&self, id: i64) -> Option<Friend> {
+        self.0.friend_list.get(&id).map(|f| f.clone())
+    }
+
+    pub async fn find_friend(&self, id: i64) -> Option<Friend> {
+        if let Some(f) = self.cached_friend(id) {
+            return Some(f);
+        }
+
+        if let Err(e) = self.refresh_friend_list().await {
+            error!("{}刷新好友列表失败: {:?}", self, e);
+            return None;
+        }
+
+        self.0.friend_list.get(&id).map(|f| f.clone())
+    }
+
     pub fn work_dir(&self) -> PathBuf {
         self.0.work_dir.clone()
     }
@@ -157,30 +309,35 @@ impl Display for Bot {
 }
 
 mod imp {
-    use std::path::{Path, PathBuf};
-    use std::sync::atomic::AtomicBool;
-    use std::sync::Arc;
+    use std::net::SocketAddr;
+    use std::path::PathBuf;
+    use std::sync::{Arc, RwLock};
 
     use dashmap::DashMap;
-    use ricq::device::Device;
     use ricq::structs::AccountInfo;
     use ricq::Client;
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
-    use tokio::net::TcpSocket;
-    use tokio::task::yield_now;
+    use tokio::net::TcpStream;
     use tokio::{fs, io};
-    use tracing::error;
 
+    use crate::bot::device::load_device;
+    use crate::bot::proxy::Proxy;
+    use crate::bot::server::{connect_fastest, resolve_servers};
+    use crate::bot::status::StatusRecorder;
     use crate::bot::BotConfiguration;
     use crate::channel::GlobalEventBroadcastHandler;
+    use crate::contact::friend::Friend;
     use crate::contact::group::Group;
 
     pub struct Bot {
         pub id: i64,
-        pub enable: AtomicBool,
+        pub status: StatusRecorder,
         pub client: Arc<Client>,
         pub group_list: DashMap<i64, Group>,
+        pub friend_list: DashMap<i64, Friend>,
         pub work_dir: PathBuf,
+        pub servers: Vec<String>,
+        pub server: RwLock<Option<SocketAddr>>,
+        pub proxy: Option<Proxy>,
     }
 
     impl Bot {
@@ -200,84 +357,31 @@ mod imp {
                     .expect("Cannot create work dir");
             }
 
-            let mut file_p = work_dir.clone();
-            file_p.push("device.json");
-            let device: Device = if file_p.is_file() {
-                if let Ok(fu) = fs::File::open(&file_p).await.map(|mut f| async move {
-                    let mut str = String::new();
-                    f.read_to_string(&mut str).await.expect("Cannot read file");
-
-                    match serde_json::from_str(&str) {
-                        Ok(d) => d,
-                        Err(e) => {
-                            error!("{:?}", e);
-                            let d = Device::random();
-
-                            let mut bak = file_p.as_os_str().to_owned();
-                            bak.push(".bak");
-                            let bak = Path::new(&bak);
-                            if let Err(_) = fs::copy(&file_p, &bak).await {
-                                return d;
-                            };
-
-                            drop(f); // make sure the file is closed
-                            if let Ok(mut f) = fs::File::create(file_p).await {
-                                let s = serde_json::to_string_pretty(&d)
-                                    .expect("Cannot serialize device info");
-
-                                f.write_all(s.as_bytes())
-                                    .await
-                                    .expect("Cannot write device info to file");
-                            };
-
-                            d
-                        }
-                    }
-                }) {
-                    fu.await
-                } else {
-                    Device::random()
-                }
-            } else {
-                let d = Device::random();
-
-                if let Ok(mut f) = fs::File::create(&file_p).await {
-                    let s = serde_json::to_string_pretty(&d).unwrap();
-
-                    f.write_all(s.as_bytes())
-                        .await
-                        .expect("Cannot write device info to file")
-                }
-
-                d
-            };
+            let device = load_device(id, &work_dir).await;
 
             let client = Client::new(device, conf.version, GlobalEventBroadcastHandler);
             let client = Arc::new(client);
 
             Self {
                 id,
-                enable: AtomicBool::new(false),
+                status: StatusRecorder::default(),
                 group_list: DashMap::new(),
+                friend_list: DashMap::new(),
                 client,
                 work_dir,
+                servers: conf.servers,
+                server: RwLock::new(None),
+                proxy: conf.proxy,
             }
         }
 
-        pub async fn start(&self) -> io::Result<()> {
-            let client = self.client.clone();
-
-            //let addr = SocketAddr::new(Ipv4Addr::new(113, 96, 18, 253).into(), 80);
-            let socket = TcpSocket::new_v4()?;
-            let stream = socket.connect(client.get_address_list().await[0]).await?;
-            //let stream = TcpStream::connect(client.get_address()).await?;
-
-            tokio::spawn(async move {
-                client.start(stream).await;
-            });
-            yield_now().await;
+        /// 连接服务器, 连接建立后由调用者启动客户端
+        pub async fn connect(&self) -> io::Result<TcpStream> {
+            let addrs = resolve_servers(&self.client, &self.servers).await;
+            let (stream, addr) = connect_fastest(&addrs, self.proxy.as_ref()).await?;
+            *self.server.write().expect("Cannot write server") = Some(addr);
 
-            Ok(())
+            Ok(stream)
         }
 
         pub async fn nickname(&self) -> String {
@@ -299,4 +403,7 @@ mod imp {
 pub struct BotConfiguration {
     pub work_dir: Option<PathBuf>,
     pub version: ricq::version::Version,
+    /// 自定义服务器列表, 为空时使用ricq获取的服务器列表
+    pub servers: Vec<String>,
+    pub proxy: Option<proxy::Proxy>,
 }
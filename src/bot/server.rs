@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use ricq::Client;
+use tokio::net::{lookup_host, TcpStream};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::bot::proxy::Proxy;
+use crate::get_runtime;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 解析配置的服务器列表, 支持`host:port`与IPv6地址(`[::1]:8080`), 未配置时使用ricq获取的服务器列表;
+/// 重复的地址只保留第一个, 其余地址保持配置中的顺序
+pub async fn resolve_servers(client: &Client, servers: &[String]) -> Vec<SocketAddr> {
+    if servers.is_empty() {
+        return client.get_address_list().await;
+    }
+
+    let mut addrs = vec![];
+    for server in servers {
+        match lookup_host(server.as_str()).await {
+            Ok(resolved) => addrs.extend(resolved),
+            Err(e) => warn!("解析服务器地址{}失败: {}", server, e),
+        }
+    }
+
+    let mut seen = HashSet::new();
+    addrs.retain(|addr| seen.insert(*addr));
+    addrs
+}
+
+async fn probe(addr: SocketAddr, proxy: Option<Proxy>) -> io::Result<(TcpStream, Duration)> {
+    let start = Instant::now();
+    let connect = async {
+        match proxy {
+            Some(proxy) => proxy.connect(addr).await,
+            None => TcpStream::connect(addr).await,
+        }
+    };
+    let stream = tokio::time::timeout(CONNECT_TIMEOUT, connect)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "connect timed out"))??;
+
+    Ok((stream, start.elapsed()))
+}
+
+/// 同时连接所有服务器, 使用延迟最低的连接, 其余连接将被关闭
+///
+/// 仅在建立连接时切换服务器: 连接建立后断开不会自动切换到其他服务器,
+/// Bot将变为离线状态, 需要使用'bot relogin'重新登陆
+pub async fn connect_fastest(
+    addrs: &[SocketAddr],
+    proxy: Option<&Proxy>,
+) -> io::Result<(TcpStream, SocketAddr)> {
+    if addrs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no server address available",
+        ));
+    }
+
+    let (tx, mut rx) = mpsc::channel(addrs.len());
+    let probes: Vec<_> = addrs
+        .iter()
+        .map(|&addr| {
+            let tx = tx.clone();
+            let proxy = proxy.cloned();
+            get_runtime().spawn(async move {
+                let _ = tx.send((addr, probe(addr, proxy).await)).await;
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut last_error = None;
+    while let Some((addr, result)) = rx.recv().await {
+        match result {
+            Ok((stream, latency)) => {
+                info!("已连接至服务器{}, 延迟{}ms", addr, latency.as_millis());
+                for probe in probes {
+                    probe.abort();
+                }
+                return Ok((stream, addr));
+            }
+            Err(e) => {
+                warn!("连接服务器{}失败: {}, 尝试下一个服务器", addr, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::Other, "cannot connect")))
+}
@@ -0,0 +1,158 @@
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicU8, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BotState {
+    Offline,
+    Connecting,
+    Online,
+}
+
+impl BotState {
+    fn from_u8(state: u8) -> Self {
+        match state {
+            1 => Self::Connecting,
+            2 => Self::Online,
+            _ => Self::Offline,
+        }
+    }
+}
+
+impl Display for BotState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Offline => f.write_str("offline"),
+            Self::Connecting => f.write_str("connecting"),
+            Self::Online => f.write_str("online"),
+        }
+    }
+}
+
+/// Bot状态的快照, 时间均为unix时间戳(秒)
+#[derive(Debug, Clone, Serialize)]
+pub struct BotStatus {
+    pub id: i64,
+    pub state: BotState,
+    pub login_time: Option<i64>,
+    /// 心跳运行且连接在线的最后确认时间
+    pub last_heartbeat: Option<i64>,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+    pub server: Option<SocketAddr>,
+}
+
+impl BotStatus {
+    pub fn is_online(&self) -> bool {
+        self.state == BotState::Online
+    }
+}
+
+pub(crate) fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// 0表示无记录
+fn optional_time(time: &AtomicI64) -> Option<i64> {
+    match time.load(Ordering::Relaxed) {
+        0 => None,
+        t => Some(t),
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct StatusRecorder {
+    state: AtomicU8,
+    login_time: AtomicI64,
+    last_heartbeat: AtomicI64,
+    messages_sent: AtomicU64,
+    messages_received: AtomicU64,
+}
+
+impl StatusRecorder {
+    pub fn state(&self) -> BotState {
+        BotState::from_u8(self.state.load(Ordering::Relaxed))
+    }
+
+    pub fn set_state(&self, state: BotState) {
+        self.state.store(state as u8, Ordering::Relaxed);
+
+        if state == BotState::Online {
+            self.login_time.store(unix_now(), Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_heartbeat(&self) {
+        self.last_heartbeat.store(unix_now(), Ordering::Relaxed);
+    }
+
+    pub fn record_sent(&self) {
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_received(&self) {
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self, id: i64, server: Option<SocketAddr>) -> BotStatus {
+        BotStatus {
+            id,
+            state: self.state(),
+            login_time: optional_time(&self.login_time),
+            last_heartbeat: optional_time(&self.last_heartbeat),
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            messages_received: self.messages_received.load(Ordering::Relaxed),
+            server,
+        }
+    }
+}
+
+/// 所有Bot状态的汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct AppStatus {
+    pub bots: Vec<BotStatus>,
+    pub online: usize,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+}
+
+impl From<Vec<BotStatus>> for AppStatus {
+    fn from(bots: Vec<BotStatus>) -> Self {
+        Self {
+            online: bots.iter().filter(|s| s.is_online()).count(),
+            messages_sent: bots.iter().map(|s| s.messages_sent).sum(),
+            messages_received: bots.iter().map(|s| s.messages_received).sum(),
+            bots,
+        }
+    }
+}
+
+/// 将时间戳格式化为距今的时长, 如`1h3m12s`
+pub fn format_elapsed(time: i64) -> String {
+    let secs = (unix_now() - time).max(0);
+    let (d, h, m, s) = (
+        secs / 86400,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    );
+
+    let mut out = String::new();
+    if d > 0 {
+        out.push_str(&format!("{}d", d));
+    }
+    if d > 0 || h > 0 {
+        out.push_str(&format!("{}h", h));
+    }
+    if d > 0 || h > 0 || m > 0 {
+        out.push_str(&format!("{}m", m));
+    }
+    out.push_str(&format!("{}s", s));
+    out
+}
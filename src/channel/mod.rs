@@ -1,14 +1,17 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::OnceLock;
 
 use async_trait::async_trait;
 use regex::Regex;
 use ricq::handler::QEvent;
 use tokio::sync::broadcast::{channel, Receiver, Sender};
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::event::{BotOnlineEvent, Event, EventInner, GroupMessageEvent};
+use crate::event::{
+    BotOnlineEvent, Event, EventInner, FriendMessageEvent, GroupMessageEvent, TempMessageEvent,
+};
 use crate::service::listeners::get_global_worker;
-use crate::{get_app, get_listener_runtime, Bot};
+use crate::{get_app, Bot};
 
 static GLOBAL_EVENT_CHANNEL: OnceLock<Sender<Event>> = OnceLock::<Sender<Event>>::new();
 
@@ -24,11 +27,26 @@ pub fn global_receiver() -> Receiver<Event> {
     global_sender().subscribe()
 }
 
+static ACCEPT_EVENTS: AtomicBool = AtomicBool::new(true);
+
+/// 关闭时调用, 之后收到的事件将被忽略
+pub fn stop_accepting_events() {
+    ACCEPT_EVENTS.store(false, Ordering::Release);
+}
+
 pub struct GlobalEventBroadcastHandler;
 
 #[async_trait]
 impl ricq::handler::Handler for GlobalEventBroadcastHandler {
     async fn handle(&self, event: QEvent) {
+        if !ACCEPT_EVENTS.load(Ordering::Acquire) {
+            return;
+        }
+
+        // 在第一个await之前取号, 事件按进入此方法的顺序交给监听器;
+        // ricq同时调用此方法时, 同时进入的事件之间的顺序不确定
+        let arrival = get_global_worker().reserve();
+
         let bot_id: i64;
         let bot: Bot;
 
@@ -67,6 +85,7 @@ impl ricq::handler::Handler for GlobalEventBroadcastHandler {
                     return;
                 };
 
+                bot.record_received();
                 let group = bot.find_group(e.inner.group_code).await.unwrap();
 
                 let filter = get_filter_regex();
@@ -92,22 +111,59 @@ impl ricq::handler::Handler for GlobalEventBroadcastHandler {
                     return;
                 };
 
+                bot.record_received();
                 info!(
                     "好友 {}({}) >> {bot}: {}",
                     e.inner.from_uin, e.inner.from_nick, e.inner.elements,
                 );
 
-                self_event = Event::Unknown(EventInner::<QEvent>::from(QEvent::FriendMessage(e)))
+                self_event = Event::FriendMessageEvent(FriendMessageEvent::from(bot, e));
+            }
+            QEvent::TempMessage(e) => {
+                bot_id = e.client.uin().await;
+                if bot_id == e.inner.from_uin {
+                    return;
+                }
+                bot = if let Some(b) = get_bot(bot_id) {
+                    b
+                } else {
+                    return;
+                };
+
+                bot.record_received();
+                info!(
+                    "临时会话 {}({}) 来自群{} >> {bot}: {}",
+                    e.inner.from_uin, e.inner.from_nick, e.inner.group_code, e.inner.elements,
+                );
+
+                self_event = Event::TempMessageEvent(TempMessageEvent::from(bot, e));
+            }
+            QEvent::KickedOffline(e) => {
+                bot_id = e.client.uin().await;
+                if let Some(b) = get_bot(bot_id) {
+                    warn!("{}被踢下线: {:?}", b, e.inner);
+                    b.set_online(false);
+                }
+                get_app().on_bot_offline(bot_id);
+
+                self_event = Event::Unknown(EventInner::<QEvent>::from(QEvent::KickedOffline(e)));
+            }
+            QEvent::MSFOffline(e) => {
+                bot_id = e.client.uin().await;
+                if let Some(b) = get_bot(bot_id) {
+                    warn!("{}被服务器强制下线: {:?}", b, e.inner);
+                    b.set_online(false);
+                }
+                get_app().on_bot_offline(bot_id);
+
+                self_event = Event::Unknown(EventInner::<QEvent>::from(QEvent::MSFOffline(e)));
             }
             or => {
                 self_event = Event::Unknown(EventInner::<QEvent>::from(or));
             }
         }
 
-        let e = self_event.clone();
-        get_listener_runtime().spawn(async move {
-            get_global_worker().handle(&e).await;
-        });
+        get_global_worker().dispatch_reserved(arrival, self_event.clone());
 
         let _ = global_sender().send(self_event);
     }
@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+pub static DEFAULT_CONFIG: &[u8] = include_bytes!("../../default_config/default_http_conf.toml");
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct HttpConfig {
+    pub proxy: Option<String>,
+}
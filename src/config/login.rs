@@ -2,23 +2,43 @@ use serde::{Deserialize, Serialize};
 
 pub static DEFAULT_CONFIG: &[u8] = include_bytes!("../../default_config/default_login_conf.toml");
 
-#[derive(Deserialize, Serialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
 pub struct LoginConfig {
     pub default_protocol: Protocol,
     #[serde(rename = "bot")]
     pub bots: Vec<BotConfig>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct BotConfig {
     pub account: i64,
     pub password: Option<String>,
+    pub password_md5: Option<String>,
     pub protocol: Option<Protocol>,
+    /// 自定义协议版本的名称, 配置后忽略`protocol`
+    pub version: Option<String>,
     #[serde(default = "true_bool")]
     pub auto_login: bool,
+    #[serde(default)]
+    pub login_mode: LoginMode,
+    #[serde(default)]
+    pub servers: Vec<String>,
+    pub proxy: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginMode {
+    Password,
+    QRCode,
+}
+
+impl Default for LoginMode {
+    fn default() -> Self {
+        Self::Password
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Protocol {
     IPAD,
     AndroidPhone,
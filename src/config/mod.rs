@@ -1,6 +1,10 @@
 use std::path::PathBuf;
 
+pub mod http;
 pub mod login;
+pub mod protocol;
+pub mod routing;
+pub mod runtime;
 
 static SERVICE_CONFIG_PATH: &str = "service";
 
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::login::Protocol;
+
+pub static DEFAULT_CONFIG: &[u8] = include_bytes!("../../default_config/default_protocols.toml");
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct ProtocolsConfig {
+    #[serde(rename = "version", default)]
+    pub versions: Vec<VersionConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct VersionConfig {
+    pub name: String,
+    pub protocol: Protocol,
+    pub apk_id: String,
+    pub app_id: u32,
+    pub sub_app_id: Option<u32>,
+    pub sort_version_name: String,
+    pub build_ver: String,
+    pub build_time: u32,
+    /// 十六进制字符串
+    pub apk_sign: String,
+    pub sdk_version: String,
+    pub sso_version: u32,
+    pub misc_bitmap: u32,
+    pub sub_sig_map: u32,
+    pub main_sig_map: u32,
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+pub static DEFAULT_CONFIG: &[u8] = include_bytes!("../../default_config/default_routing_conf.toml");
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct RoutingConfig {
+    #[serde(default)]
+    pub strategy: RoutingStrategy,
+    #[serde(default)]
+    pub priority: Vec<i64>,
+    #[serde(default)]
+    pub fixed: Vec<FixedRoute>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct FixedRoute {
+    pub group: i64,
+    pub bot: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutingStrategy {
+    FirstSeen,
+    Fixed,
+    LeastLoaded,
+    Priority,
+}
+
+impl Default for RoutingStrategy {
+    fn default() -> Self {
+        Self::FirstSeen
+    }
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+pub static DEFAULT_CONFIG: &[u8] = include_bytes!("../../default_config/default_runtime_conf.toml");
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    pub global: PoolConfig,
+    pub listener: PoolConfig,
+    pub plugin: PoolConfig,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy)]
+#[serde(default)]
+pub struct PoolConfig {
+    pub worker_threads: Option<usize>,
+}
@@ -0,0 +1,107 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use ricq::msg::elem::FriendImage;
+use ricq::structs::{FriendInfo, MessageReceipt};
+use ricq::RQResult;
+use tracing::error;
+
+use crate::event::send::{post_send, pre_send, MessageTarget};
+use crate::{Bot, MessageChain};
+
+#[derive(Clone)]
+pub struct Friend(Arc<imp::Friend>);
+
+impl Friend {
+    pub fn from(bot: Bot, info: FriendInfo) -> Self {
+        let imp = imp::Friend {
+            id: info.uin,
+            bot,
+            info,
+        };
+
+        Self(Arc::new(imp))
+    }
+
+    pub fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    pub fn bot(&self) -> &Bot {
+        &self.0.bot
+    }
+
+    pub fn nickname(&self) -> &str {
+        &self.0.info.nick
+    }
+
+    pub fn remark(&self) -> &str {
+        &self.0.info.remark
+    }
+
+    pub async fn send_message(&self, chain: MessageChain) -> RQResult<MessageReceipt> {
+        let target = MessageTarget::Friend(self.clone());
+        let chain = pre_send(&target, chain).await?;
+
+        let result = self
+            .bot()
+            .client()
+            .send_friend_message(self.id(), chain.clone())
+            .await;
+
+        match result {
+            Ok(_) => self.bot().record_sent(),
+            Err(ref err) => {
+                error!(
+                    "{}发送信息失败, 目标好友: {}({}), {:?}",
+                    self.bot(),
+                    self.nickname(),
+                    self.id(),
+                    err
+                )
+            }
+        }
+
+        post_send(target, chain, result.as_ref().map(Some));
+
+        result
+    }
+
+    pub async fn upload_image(&self, image: Vec<u8>) -> RQResult<FriendImage> {
+        let result = self
+            .bot()
+            .client()
+            .upload_friend_image(self.id(), image)
+            .await;
+
+        if let Err(ref err) = result {
+            error!(
+                "{}上传图片失败, 目标好友: {}({}), {:?}",
+                self.bot(),
+                self.nickname(),
+                self.id(),
+                err
+            )
+        }
+
+        result
+    }
+}
+
+impl Display for Friend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Friend({})", self.id())
+    }
+}
+
+mod imp {
+    use ricq::structs::FriendInfo;
+
+    use crate::Bot;
+
+    pub struct Friend {
+        pub id: i64,
+        pub bot: Bot,
+        pub info: FriendInfo,
+    }
+}
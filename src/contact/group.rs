@@ -8,6 +8,7 @@ use ricq::RQResult;
 use tracing::error;
 
 use crate::contact::member::NamedMember;
+use crate::event::send::{post_send, pre_send, MessageTarget};
 use crate::{Bot, GroupMemberInfo, MessageChain};
 
 #[derive(Clone)]
@@ -37,6 +38,28 @@ impl Group {
         &self.0.info.name
     }
 
+    pub fn member_count(&self) -> u16 {
+        self.0.info.member_count
+    }
+
+    pub async fn refresh_member_list(&self) -> RQResult<Vec<NamedMember>> {
+        let infos = self
+            .bot()
+            .client()
+            .get_group_member_list(self.id(), self.0.info.owner_uin)
+            .await?;
+
+        self.0.members.clear();
+        let mut members = Vec::with_capacity(infos.len());
+        for info in infos {
+            let member = NamedMember::from(self.clone(), info);
+            self.0.members.insert(member.id(), member.clone());
+            members.push(member);
+        }
+
+        Ok(members)
+    }
+
     pub async fn find_member(&self, id: i64) -> Option<NamedMember> {
         if let Some(member) = self.0.members.get(&id) {
             return Some(member.clone());
@@ -57,22 +80,30 @@ impl Group {
     }
 
     pub async fn send_message(&self, chain: MessageChain) -> RQResult<MessageReceipt> {
+        let target = MessageTarget::Group(self.clone());
+        let chain = pre_send(&target, chain).await?;
+
         let result = self
             .bot()
             .client()
-            .send_group_message(self.id(), chain)
+            .send_group_message(self.id(), chain.clone())
             .await;
 
-        if let Err(ref err) = result {
-            error!(
-                "{}发送信息失败, 目标群: {}({}), {:?}",
-                self.bot(),
-                self.name(),
-                self.id(),
-                err
-            )
+        match result {
+            Ok(_) => self.bot().record_sent(),
+            Err(ref err) => {
+                error!(
+                    "{}发送信息失败, 目标群: {}({}), {:?}",
+                    self.bot(),
+                    self.name(),
+                    self.id(),
+                    err
+                )
+            }
         }
 
+        post_send(target, chain, result.as_ref().map(Some));
+
         result
     }
 
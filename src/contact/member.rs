@@ -1,6 +1,10 @@
 use crate::contact::group::Group;
-use crate::GroupMemberInfo;
+use crate::event::send::{post_send, pre_send, MessageTarget};
+use crate::{Bot, GroupMemberInfo, MessageChain};
+use ricq::structs::GroupMemberPermission;
+use ricq::RQResult;
 use std::sync::Arc;
+use tracing::error;
 
 #[derive(Clone)]
 pub struct NamedMember(Arc<imp::NamedMember>);
@@ -24,14 +28,83 @@ impl NamedMember {
         &self.0.info.card_name
     }
 
+    pub fn special_title(&self) -> &str {
+        &self.0.info.special_title
+    }
+
+    pub fn level(&self) -> u16 {
+        self.0.info.level
+    }
+
+    pub fn join_time(&self) -> i64 {
+        self.0.info.join_time
+    }
+
+    pub fn permission(&self) -> &GroupMemberPermission {
+        &self.0.info.permission
+    }
+
     pub fn group(&self) -> &Group {
         &self.0.group
     }
 }
 
+/// 通过群发起临时会话的成员
+#[derive(Clone)]
+pub struct TempMember(Arc<imp::TempMember>);
+
+impl TempMember {
+    pub fn from(bot: Bot, group_code: i64, uin: i64) -> Self {
+        let inner = imp::TempMember {
+            bot,
+            group_code,
+            uin,
+        };
+
+        Self(inner.into())
+    }
+
+    pub fn id(&self) -> i64 {
+        self.0.uin
+    }
+
+    /// 发起临时会话的群号
+    pub fn group_code(&self) -> i64 {
+        self.0.group_code
+    }
+
+    pub fn bot(&self) -> &Bot {
+        &self.0.bot
+    }
+
+    pub async fn send_message(&self, chain: MessageChain) -> RQResult<()> {
+        let target = MessageTarget::Temp(self.clone());
+        let chain = pre_send(&target, chain).await?;
+
+        let result = self
+            .bot()
+            .client()
+            .send_temp_message(self.group_code(), self.id(), chain.clone())
+            .await;
+
+        match result {
+            Ok(_) => self.bot().record_sent(),
+            Err(ref err) => error!(
+                "{}发送信息失败, 目标临时会话: {}, {:?}",
+                self.bot(),
+                self.id(),
+                err
+            ),
+        }
+
+        post_send(target, chain, result.as_ref().map(|_| None));
+        result.map(|_| ())
+    }
+}
+
 mod imp {
     use crate::contact::group::Group;
-    use crate::GroupMemberInfo;
+    use crate::{Bot, GroupMemberInfo};
 
     pub struct NamedMember {
         pub group: Group,
@@ -39,4 +112,10 @@ mod imp {
     }
 
     pub struct AnonymousMember;
+
+    pub struct TempMember {
+        pub bot: Bot,
+        pub group_code: i64,
+        pub uin: i64,
+    }
 }
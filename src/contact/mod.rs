@@ -1,11 +1,13 @@
+use crate::contact::friend::Friend;
 use crate::contact::group::Group;
 use crate::MessageChain;
 
+pub mod friend;
 pub mod group;
 pub mod member;
 
 pub enum Contact {
-    Friend,
+    Friend(Friend),
     Group(Group),
     Stranger,
 }
@@ -13,7 +15,9 @@ pub enum Contact {
 impl Contact {
     pub async fn send_message(&self, chain: MessageChain) {
         match self {
-            Self::Friend => {}
+            Self::Friend(f) => {
+                f.send_message(chain).await.ok();
+            }
             Self::Group(g) => {
                 g.send_message(chain).await.ok();
             }
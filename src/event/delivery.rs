@@ -0,0 +1,297 @@
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+use dashmap::DashMap;
+use tokio::sync::Notify;
+
+use crate::event::Event;
+
+/// 会话的标识, 通常为(群号, QQ号), 不区分的部分为0
+pub type ConversationKey = (i64, i64);
+
+type KeyFn = Arc<dyn Fn(&Event) -> Option<ConversationKey> + Send + Sync>;
+
+/// 监听器接收事件的方式;
+/// 同步处理的事件(如[`MessagePreSendEvent`](crate::event::send::MessagePreSendEvent))不受限制
+#[derive(Clone)]
+pub enum DeliveryMode {
+    /// 同时处理多个事件, 不保证顺序
+    Concurrent,
+    /// 同一时间只处理一个事件, 按接收顺序处理
+    PerListener,
+    /// 同一会话的事件按接收顺序依次处理, 不同会话之间并发处理;
+    /// 返回`None`的事件不受限制
+    PerConversation(KeyFn),
+}
+
+impl DeliveryMode {
+    pub fn per_conversation<F>(key: F) -> Self
+    where
+        F: Fn(&Event) -> Option<ConversationKey> + Send + Sync + 'static,
+    {
+        Self::PerConversation(Arc::new(key))
+    }
+
+    /// 同一个群的消息依次处理
+    pub fn per_group() -> Self {
+        Self::per_conversation(|e| match e {
+            Event::GroupMessageEvent(e) => Some((e.group().id(), 0)),
+            _ => None,
+        })
+    }
+
+    /// 同一个用户在同一个聊天中的消息依次处理
+    pub fn per_user() -> Self {
+        Self::per_conversation(|e| match e {
+            Event::GroupMessageEvent(e) => Some((e.group().id(), e.message().from_uin)),
+            Event::FriendMessageEvent(e) => Some((0, e.message().from_uin)),
+            Event::TempMessageEvent(e) => Some((e.message().group_code, e.message().from_uin)),
+            _ => None,
+        })
+    }
+}
+
+impl Default for DeliveryMode {
+    fn default() -> Self {
+        Self::Concurrent
+    }
+}
+
+#[derive(Default)]
+struct SequencerState {
+    next: u64,
+    serving: u64,
+    /// 未轮到就已结束的号
+    finished: BTreeSet<u64>,
+}
+
+/// 按取号顺序依次放行
+#[derive(Default)]
+pub(crate) struct Sequencer {
+    state: Mutex<SequencerState>,
+    notify: Notify,
+}
+
+impl Sequencer {
+    pub fn take(self: &Arc<Self>) -> Ticket {
+        let mut state = self.state.lock().expect("Cannot lock sequencer");
+        let number = state.next;
+        state.next += 1;
+
+        Ticket {
+            sequencer: self.clone(),
+            number,
+        }
+    }
+
+    fn finish(&self, number: u64) {
+        let mut state = self.state.lock().expect("Cannot lock sequencer");
+        if number != state.serving {
+            state.finished.insert(number);
+            return;
+        }
+
+        state.serving += 1;
+        loop {
+            let serving = state.serving;
+            if !state.finished.remove(&serving) {
+                break;
+            }
+            state.serving += 1;
+        }
+        drop(state);
+
+        self.notify.notify_waiters();
+    }
+}
+
+/// 处理顺序的凭证, 丢弃时放行下一个事件
+pub(crate) struct Ticket {
+    sequencer: Arc<Sequencer>,
+    number: u64,
+}
+
+impl Ticket {
+    /// 等待之前的事件处理完成
+    pub async fn wait_turn(&self) {
+        loop {
+            let notified = self.sequencer.notify.notified();
+            {
+                let state = self.sequencer.state.lock().expect("Cannot lock sequencer");
+                if state.serving == self.number {
+                    return;
+                }
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        self.sequencer.finish(self.number);
+    }
+}
+
+const CLEANUP_THRESHOLD: usize = 64;
+
+/// 监听器的顺序控制状态
+pub(crate) enum Delivery {
+    Concurrent,
+    PerListener(Arc<Sequencer>),
+    PerConversation {
+        key: KeyFn,
+        sequencers: DashMap<ConversationKey, Arc<Sequencer>>,
+    },
+}
+
+impl From<DeliveryMode> for Delivery {
+    fn from(mode: DeliveryMode) -> Self {
+        match mode {
+            DeliveryMode::Concurrent => Self::Concurrent,
+            DeliveryMode::PerListener => Self::PerListener(Default::default()),
+            DeliveryMode::PerConversation(key) => Self::PerConversation {
+                key,
+                sequencers: DashMap::new(),
+            },
+        }
+    }
+}
+
+impl Delivery {
+    /// 在接收事件时按顺序取号
+    pub fn reserve(&self, event: &Event) -> Option<Ticket> {
+        match self {
+            Self::Concurrent => None,
+            Self::PerListener(sequencer) => Some(sequencer.take()),
+            Self::PerConversation { key, sequencers } => {
+                let key = key(event)?;
+
+                // 没有未处理事件的会话不再持有号, 数量较多时清理
+                if sequencers.len() > CLEANUP_THRESHOLD {
+                    sequencers.retain(|k, s| *k == key || Arc::strong_count(s) > 1);
+                }
+
+                Some(sequencers.entry(key).or_default().take())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::{Delivery, DeliveryMode, Sequencer, Ticket, CLEANUP_THRESHOLD};
+    use crate::event::testing::{bot, friend_message, text};
+
+    const WAIT: Duration = Duration::from_millis(50);
+
+    async fn ready<F: Future>(future: F) -> bool {
+        tokio::time::timeout(WAIT, future).await.is_ok()
+    }
+
+    #[tokio::test]
+    async fn wait_turn_in_take_order() {
+        let sequencer = Arc::new(Sequencer::default());
+        let tickets: Vec<Ticket> = (0..4).map(|_| sequencer.take()).collect();
+        let order = Arc::new(Mutex::new(vec![]));
+
+        // 后取号的先开始等待
+        let handles: Vec<_> = tickets
+            .into_iter()
+            .enumerate()
+            .rev()
+            .map(|(i, ticket)| {
+                let order = order.clone();
+                tokio::spawn(async move {
+                    ticket.wait_turn().await;
+                    order.lock().unwrap().push(i);
+                    tokio::task::yield_now().await;
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(*order.lock().unwrap(), [0, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn dropped_ticket_releases_next() {
+        let sequencer = Arc::new(Sequencer::default());
+        let first = sequencer.take();
+        let second = sequencer.take();
+        let third = sequencer.take();
+
+        assert!(!ready(second.wait_turn()).await);
+        drop(first);
+        assert!(ready(second.wait_turn()).await);
+
+        // 未轮到就丢弃的号在轮到时直接跳过
+        let fourth = sequencer.take();
+        drop(third);
+        assert!(!ready(fourth.wait_turn()).await);
+        drop(second);
+        assert!(ready(fourth.wait_turn()).await);
+    }
+
+    #[tokio::test]
+    async fn conversations_are_independent() {
+        let bot = bot(30001).await;
+        let delivery = Delivery::from(DeliveryMode::per_user());
+
+        let a1 = delivery
+            .reserve(&friend_message(&bot, 1, text("a1")))
+            .unwrap();
+        let b1 = delivery
+            .reserve(&friend_message(&bot, 2, text("b1")))
+            .unwrap();
+        let a2 = delivery
+            .reserve(&friend_message(&bot, 1, text("a2")))
+            .unwrap();
+
+        assert!(ready(a1.wait_turn()).await);
+        assert!(ready(b1.wait_turn()).await);
+        assert!(!ready(a2.wait_turn()).await);
+
+        drop(b1);
+        assert!(!ready(a2.wait_turn()).await);
+        drop(a1);
+        assert!(ready(a2.wait_turn()).await);
+    }
+
+    #[tokio::test]
+    async fn idle_conversations_are_cleaned_up() {
+        let bot = bot(30002).await;
+        let delivery = Delivery::from(DeliveryMode::per_user());
+        let sequencers = match delivery {
+            Delivery::PerConversation { ref sequencers, .. } => sequencers,
+            _ => unreachable!(),
+        };
+
+        let held = delivery.reserve(&friend_message(&bot, 0, text("held")));
+        for user in 1..=CLEANUP_THRESHOLD as i64 {
+            drop(delivery.reserve(&friend_message(&bot, user, text("idle"))));
+        }
+        assert_eq!(sequencers.len(), CLEANUP_THRESHOLD + 1);
+
+        // 超过阈值后, 只保留仍有未处理事件的会话与当前会话
+        let current = delivery.reserve(&friend_message(&bot, -1, text("current")));
+        assert_eq!(sequencers.len(), 2);
+        assert!(sequencers.contains_key(&(0, 0)));
+        assert!(sequencers.contains_key(&(0, -1)));
+
+        // 保留的会话仍按顺序处理
+        let next = delivery
+            .reserve(&friend_message(&bot, 0, text("next")))
+            .unwrap();
+        assert!(!ready(next.wait_turn()).await);
+        drop(held);
+        assert!(ready(next.wait_turn()).await);
+        drop(current);
+    }
+}
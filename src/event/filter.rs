@@ -0,0 +1,285 @@
+use std::ops::Not;
+use std::sync::Arc;
+
+use regex::Regex;
+use ricq::msg::elem::RQElem;
+use ricq::msg::MessageChain;
+use ricq::pb::msg::elem::Elem;
+
+use crate::event::Event;
+
+/// 事件过滤器, 在创建处理任务前检查, 不匹配的事件不会交给监听器处理
+#[derive(Clone)]
+pub enum Filter {
+    /// 来自指定群的消息, 包括通过该群发起的临时会话
+    InGroup(i64),
+    /// 指定用户发送的消息
+    FromUser(i64),
+    /// 消息中@了Bot
+    MentionsBot,
+    /// 消息中的文本匹配正则表达式
+    TextMatches(Regex),
+    /// 消息中包含图片
+    HasImage,
+    /// 指定Bot收到的事件
+    ByBot(i64),
+    Custom(Arc<dyn Fn(&Event) -> bool + Send + Sync>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    pub fn in_group(group_id: i64) -> Self {
+        Self::InGroup(group_id)
+    }
+
+    pub fn from_user(uin: i64) -> Self {
+        Self::FromUser(uin)
+    }
+
+    pub fn mentions_bot() -> Self {
+        Self::MentionsBot
+    }
+
+    pub fn text_matches(regex: Regex) -> Self {
+        Self::TextMatches(regex)
+    }
+
+    pub fn has_image() -> Self {
+        Self::HasImage
+    }
+
+    pub fn by_bot(bot_id: i64) -> Self {
+        Self::ByBot(bot_id)
+    }
+
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(&Event) -> bool + Send + Sync + 'static,
+    {
+        Self::Custom(Arc::new(f))
+    }
+
+    pub fn and(self, other: Filter) -> Self {
+        Self::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Filter) -> Self {
+        Self::Or(Box::new(self), Box::new(other))
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+
+    pub fn matches(&self, event: &Event) -> bool {
+        match self {
+            Self::InGroup(id) => group_id(event) == Some(*id),
+            Self::FromUser(uin) => sender(event) == Some(*uin),
+            Self::MentionsBot => match (message(event), bot_id(event)) {
+                (Some(chain), Some(bot)) => {
+                    text_elems(chain).any(|elem| matches!(elem, RQElem::At(at) if at.target == bot))
+                }
+                _ => false,
+            },
+            Self::TextMatches(regex) => message(event)
+                .map(|chain| regex.is_match(&text_of(chain)))
+                .unwrap_or(false),
+            Self::HasImage => message(event)
+                .map(|chain| {
+                    chain
+                        .0
+                        .iter()
+                        .any(|elem| matches!(elem, Elem::CustomFace(_) | Elem::NotOnlineImage(_)))
+                })
+                .unwrap_or(false),
+            Self::ByBot(id) => bot_id(event) == Some(*id),
+            Self::Custom(f) => f(event),
+            Self::And(a, b) => a.matches(event) && b.matches(event),
+            Self::Or(a, b) => a.matches(event) || b.matches(event),
+            Self::Not(f) => !f.matches(event),
+        }
+    }
+}
+
+impl Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Self::Output {
+        Filter::not(self)
+    }
+}
+
+fn group_id(event: &Event) -> Option<i64> {
+    match event {
+        Event::GroupMessageEvent(e) => Some(e.group().id()),
+        Event::TempMessageEvent(e) => Some(e.message().group_code),
+        _ => None,
+    }
+}
+
+fn sender(event: &Event) -> Option<i64> {
+    match event {
+        Event::GroupMessageEvent(e) => Some(e.message().from_uin),
+        Event::FriendMessageEvent(e) => Some(e.message().from_uin),
+        Event::TempMessageEvent(e) => Some(e.message().from_uin),
+        _ => None,
+    }
+}
+
+fn bot_id(event: &Event) -> Option<i64> {
+    match event {
+        Event::GroupMessageEvent(e) => Some(e.bot().id()),
+        Event::FriendMessageEvent(e) => Some(e.bot().id()),
+        Event::TempMessageEvent(e) => Some(e.bot().id()),
+        Event::BotOnlineEvent(e) => Some(e.bot().id()),
+        Event::MessagePreSendEvent(e) => Some(e.bot().id()),
+        Event::MessagePostSendEvent(e) => Some(e.bot().id()),
+        Event::Unknown(_) => None,
+    }
+}
+
+fn message(event: &Event) -> Option<&MessageChain> {
+    match event {
+        Event::GroupMessageEvent(e) => Some(&e.message().elements),
+        Event::FriendMessageEvent(e) => Some(&e.message().elements),
+        Event::TempMessageEvent(e) => Some(&e.message().elements),
+        _ => None,
+    }
+}
+
+/// 文本与@都是文本元素, 只转换这些元素, 不克隆整条消息
+fn text_elems(chain: &MessageChain) -> impl Iterator<Item = RQElem> + '_ {
+    chain.0.iter().filter_map(|elem| match elem {
+        Elem::Text(t) => Some(RQElem::from(Elem::Text(t.clone()))),
+        _ => None,
+    })
+}
+
+fn text_of(chain: &MessageChain) -> String {
+    let mut text = String::new();
+    for elem in text_elems(chain) {
+        if let RQElem::Text(t) = elem {
+            text.push_str(&t.content);
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+    use ricq::msg::elem::At;
+    use ricq::pb::msg::elem::Elem;
+
+    use super::Filter;
+    use crate::event::testing::{bot, friend_message, group_message, temp_message, text, unknown};
+    use crate::event::Event;
+
+    #[tokio::test]
+    async fn in_group() {
+        let bot = bot(10001).await;
+        let filter = Filter::in_group(1);
+
+        assert!(filter.matches(&group_message(&bot, 1, 2, text("hi"))));
+        assert!(!filter.matches(&group_message(&bot, 3, 2, text("hi"))));
+        assert!(filter.matches(&temp_message(&bot, 1, 2, text("hi"))));
+        assert!(!filter.matches(&temp_message(&bot, 3, 2, text("hi"))));
+        assert!(!filter.matches(&friend_message(&bot, 2, text("hi"))));
+        assert!(!filter.matches(&unknown()));
+    }
+
+    #[tokio::test]
+    async fn from_user_and_by_bot() {
+        let bot = bot(10001).await;
+        let from = Filter::from_user(2);
+        let by = Filter::by_bot(10001);
+
+        for event in [
+            group_message(&bot, 1, 2, text("hi")),
+            friend_message(&bot, 2, text("hi")),
+            temp_message(&bot, 1, 2, text("hi")),
+        ] {
+            assert!(from.matches(&event));
+            assert!(by.matches(&event));
+            assert!(!Filter::from_user(3).matches(&event));
+            assert!(!Filter::by_bot(10002).matches(&event));
+        }
+        assert!(!from.matches(&unknown()));
+        assert!(!by.matches(&unknown()));
+    }
+
+    #[tokio::test]
+    async fn mentions_bot() {
+        let bot = bot(10001).await;
+        let mention = |target| {
+            let mut chain = text("hi ");
+            chain.push(At {
+                target,
+                display: format!("@{}", target),
+            });
+            chain
+        };
+
+        assert!(Filter::mentions_bot().matches(&group_message(&bot, 1, 2, mention(10001))));
+        assert!(!Filter::mentions_bot().matches(&group_message(&bot, 1, 2, mention(10002))));
+        assert!(!Filter::mentions_bot().matches(&group_message(&bot, 1, 2, text("@10001"))));
+    }
+
+    #[tokio::test]
+    async fn text_matches() {
+        let bot = bot(10001).await;
+        let filter = Filter::text_matches(Regex::new("^/roll \\d+$").unwrap());
+
+        assert!(filter.matches(&friend_message(&bot, 2, text("/roll 6"))));
+        assert!(!filter.matches(&friend_message(&bot, 2, text("/roll x"))));
+        assert!(!filter.matches(&unknown()));
+    }
+
+    #[tokio::test]
+    async fn has_image() {
+        let bot = bot(10001).await;
+        let mut group_image = text("look");
+        group_image.0.push(Elem::CustomFace(Default::default()));
+        let mut friend_image = text("look");
+        friend_image
+            .0
+            .push(Elem::NotOnlineImage(Default::default()));
+
+        assert!(Filter::has_image().matches(&group_message(&bot, 1, 2, group_image)));
+        assert!(Filter::has_image().matches(&friend_message(&bot, 2, friend_image)));
+        assert!(!Filter::has_image().matches(&friend_message(&bot, 2, text("look"))));
+    }
+
+    #[tokio::test]
+    async fn custom() {
+        let bot = bot(10001).await;
+        let filter = Filter::custom(|e| matches!(e, Event::FriendMessageEvent(_)));
+
+        assert!(filter.matches(&friend_message(&bot, 2, text("hi"))));
+        assert!(!filter.matches(&group_message(&bot, 1, 2, text("hi"))));
+    }
+
+    #[tokio::test]
+    async fn composition() {
+        let bot = bot(10001).await;
+        let event = group_message(&bot, 1, 2, text("hi"));
+        let yes = || Filter::in_group(1);
+        let no = || Filter::in_group(3);
+
+        assert!(yes().and(yes()).matches(&event));
+        assert!(!yes().and(no()).matches(&event));
+        assert!(!no().and(yes()).matches(&event));
+
+        assert!(yes().or(no()).matches(&event));
+        assert!(no().or(yes()).matches(&event));
+        assert!(!no().or(no()).matches(&event));
+
+        assert!(no().not().matches(&event));
+        assert!(!yes().not().matches(&event));
+        assert!((!no()).matches(&event));
+        assert!(yes().and(no().or(yes())).and(no().not()).matches(&event));
+    }
+}
@@ -1,47 +1,127 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use tokio::sync::Mutex;
+use regex::Regex;
 
+use crate::event::delivery::{Delivery, DeliveryMode};
+use crate::event::filter::Filter;
 use crate::event::FromEvent;
-use crate::service::listeners::get_global_worker;
-use crate::{get_listener_runtime, Event};
+use crate::service::listeners::{get_global_worker, ListenerInfo};
+use crate::Event;
+
+tokio::task_local! {
+    static CURRENT_LISTENER: (usize, Arc<String>);
+}
+
+/// 当前正在运行的监听器名称, 不在监听器中调用时返回`None`
+pub fn current_listener() -> Option<Arc<String>> {
+    CURRENT_LISTENER.try_with(|(_, name)| name.clone()).ok()
+}
+
+/// 当前正在运行的监听器id, 名称可能重复, 用于区分监听器
+pub(crate) fn current_listener_id() -> Option<usize> {
+    CURRENT_LISTENER.try_with(|(id, _)| *id).ok()
+}
+
+pub(crate) async fn run_as<F: Future>(id: usize, name: Arc<String>, future: F) -> F::Output {
+    CURRENT_LISTENER.scope((id, name), future).await
+}
+
+pub type ListenerError = Box<dyn Error + Send + Sync>;
+
+/// 监听器的返回值, `Ok(false)`表示关闭监听器, `Err`会被记录为一次失败
+pub type ListenerResult = Result<bool, ListenerError>;
+
+type Handler = Box<
+    dyn Fn(Event) -> Pin<Box<dyn Future<Output = ListenerResult> + Send + 'static>>
+        + Send
+        + 'static,
+>;
 
 pub struct Listener {
+    pub(crate) id: usize,
     pub(crate) name: Arc<String>,
-    pub(crate) concurrent_mutex: Option<Mutex<()>>,
-    pub(crate) handler:
-        Box<dyn Fn(Event) -> Pin<Box<dyn Future<Output = bool> + Send + 'static>> + Send + 'static>,
+    pub(crate) delivery: Delivery,
+    pub(crate) handler: Handler,
     pub(crate) closed: Arc<AtomicBool>,
     pub(crate) priority: Priority,
+    pub(crate) failures: ListenerFailures,
+    pub(crate) max_failures: Option<u32>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) filter: Option<Filter>,
+    /// 处理器接收的事件类型
+    pub(crate) accepts: fn(&Event) -> bool,
+}
+
+/// 监听器的失败计数, panic与返回的错误均计为失败
+#[derive(Default)]
+pub(crate) struct ListenerFailures {
+    total: AtomicU64,
+    consecutive: AtomicU32,
+}
+
+impl ListenerFailures {
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// 记录一次失败, 返回连续失败的次数
+    pub fn record_failure(&self) -> u32 {
+        self.total.fetch_add(1, Ordering::Relaxed);
+        self.consecutive.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn record_success(&self) {
+        self.consecutive.store(0, Ordering::Relaxed);
+    }
 }
 
 impl Listener {
-    fn new<F, Fu>(handler: F) -> ListenerBuilder
+    fn new_try<F, Fu>(handler: F) -> ListenerBuilder
     where
         F: Fn(Event) -> Fu,
         F: Send + 'static,
-        Fu: Future<Output = bool>,
+        Fu: Future<Output = ListenerResult>,
         Fu: Send + 'static,
     {
         let handler = Box::new(move |e: Event| {
             let fu = handler(e);
-            let b: Box<dyn Future<Output = bool> + Send + 'static> = Box::new(fu);
+            let b: Box<dyn Future<Output = ListenerResult> + Send + 'static> = Box::new(fu);
 
             Box::into_pin(b)
         });
 
         ListenerBuilder {
             name: None,
-            concurrent: true,
+            delivery: DeliveryMode::Concurrent,
             handler,
             closed: AtomicBool::new(false).into(),
             priority: Priority::Middle,
+            max_failures: None,
+            timeout: None,
+            filter: None,
+            accepts: accepts::<Event>,
         }
     }
 
+    fn new<F, Fu>(handler: F) -> ListenerBuilder
+    where
+        F: Fn(Event) -> Fu,
+        F: Send + 'static,
+        Fu: Future<Output = bool>,
+        Fu: Send + 'static,
+    {
+        Self::new_try(move |e: Event| {
+            let fu = handler(e);
+            async move { Ok(fu.await) }
+        })
+    }
+
     fn new_always<F, Fu>(handler: F) -> ListenerBuilder
     where
         F: Fn(Event) -> Fu,
@@ -68,7 +148,7 @@ impl Listener {
         Fu: Send + 'static,
         E: FromEvent,
     {
-        Self::new(move |e: Event| {
+        let mut builder = Self::new(move |e: Event| {
             let b: Box<dyn Future<Output = bool> + Send + 'static> =
                 if let Some(e) = E::from_event(e) {
                     let fu = handler(e);
@@ -78,7 +158,10 @@ impl Listener {
                 };
 
             Box::into_pin(b)
-        })
+        });
+        builder.accepts = accepts::<E>;
+
+        builder
     }
 
     pub fn listening_on_always<E, F, Fu>(handler: F) -> ListenerBuilder
@@ -89,7 +172,7 @@ impl Listener {
         Fu: Send + 'static,
         E: FromEvent,
     {
-        Self::new_always(move |e: Event| {
+        let mut builder = Self::new_always(move |e: Event| {
             let b: Box<dyn Future<Output = ()> + Send + 'static> = if let Some(e) = E::from_event(e)
             {
                 let fu = handler(e);
@@ -101,53 +184,128 @@ impl Listener {
             };
 
             Box::into_pin(b)
+        });
+        builder.accepts = accepts::<E>;
+
+        builder
+    }
+
+    /// 监听返回`Result`的处理器, 返回的错误会被记录, `Ok(false)`表示关闭监听器
+    pub fn listening_on_try<E, F, Fu, Err>(handler: F) -> ListenerBuilder
+    where
+        F: Fn(E) -> Fu,
+        F: Send + 'static,
+        Fu: Future<Output = Result<bool, Err>>,
+        Fu: Send + 'static,
+        Err: Into<ListenerError>,
+        E: FromEvent,
+    {
+        let mut builder = Self::new_try(move |e: Event| {
+            let b: Box<dyn Future<Output = ListenerResult> + Send + 'static> =
+                if let Some(e) = E::from_event(e) {
+                    let fu = handler(e);
+                    Box::new(async move { fu.await.map_err(Into::into) })
+                } else {
+                    Box::new(async { Ok(true) })
+                };
+
+            Box::into_pin(b)
+        });
+        builder.accepts = accepts::<E>;
+
+        builder
+    }
+
+    pub fn listening_on_always_try<E, F, Fu, Err>(handler: F) -> ListenerBuilder
+    where
+        F: Fn(E) -> Fu,
+        F: Send + 'static,
+        Fu: Future<Output = Result<(), Err>>,
+        Fu: Send + 'static,
+        Err: Into<ListenerError>,
+        E: FromEvent,
+    {
+        Self::listening_on_try(move |e: E| {
+            let fu = handler(e);
+            async move { fu.await.map(|_| true) }
         })
     }
 
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// 事件类型与过滤器均匹配时才交给此监听器
+    pub(crate) fn wants(&self, event: &Event) -> bool {
+        (self.accepts)(event) && self.filter.as_ref().map_or(true, |f| f.matches(event))
+    }
+
+    pub fn info(&self) -> ListenerInfo {
+        ListenerInfo {
+            id: self.id,
+            name: self.name.clone(),
+            priority: self.priority,
+            failures: self.failures.total(),
+        }
+    }
 }
 
 pub struct ListenerBuilder {
     pub name: Option<String>,
-    pub concurrent: bool,
-    handler:
-        Box<dyn Fn(Event) -> Pin<Box<dyn Future<Output = bool> + Send + 'static>> + Send + 'static>,
+    pub delivery: DeliveryMode,
+    handler: Handler,
     closed: Arc<AtomicBool>,
     pub priority: Priority,
+    pub max_failures: Option<u32>,
+    pub timeout: Option<Duration>,
+    pub filter: Option<Filter>,
+    accepts: fn(&Event) -> bool,
 }
 
 impl ListenerBuilder {
     pub fn start(self) -> ListenerGuard {
+        let listener = self.build();
+        let guard = ListenerGuard {
+            id: listener.id,
+            name: listener.name.clone(),
+            closed: listener.closed.clone(),
+            priority: listener.priority,
+        };
+
+        get_global_worker().register(listener);
+
+        guard
+    }
+
+    /// 构建监听器, 不注册到全局的处理器中
+    pub(crate) fn build(self) -> Listener {
         let Self {
             name,
-            concurrent,
+            delivery,
             handler,
             closed,
             priority,
+            max_failures,
+            timeout,
+            filter,
+            accepts,
         } = self;
 
-        let name = Arc::new(name.unwrap_or_else(|| String::from("Unnamed-Listener")));
-        let arc_name = name.clone();
-        let arc_closed = closed.clone();
-        let listener = Listener {
-            name,
-            concurrent_mutex: if concurrent {
-                None
-            } else {
-                Some(Mutex::new(()))
-            },
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+        Listener {
+            id,
+            name: Arc::new(name.unwrap_or_else(|| String::from("Unnamed-Listener"))),
+            delivery: delivery.into(),
             handler,
             closed,
             priority,
-        };
-
-        get_listener_runtime().spawn(get_global_worker().schedule(listener));
-
-        ListenerGuard {
-            name: arc_name,
-            closed: arc_closed,
+            failures: ListenerFailures::default(),
+            max_failures,
+            timeout,
+            filter,
+            accepts,
         }
     }
 
@@ -156,23 +314,72 @@ impl ListenerBuilder {
         self
     }
 
-    pub fn synchronize(mut self) -> Self {
-        self.concurrent = false;
+    pub fn delivery(mut self, mode: DeliveryMode) -> Self {
+        self.delivery = mode;
         self
     }
 
-    pub fn concurrent(mut self) -> Self {
-        self.concurrent = false;
-        self
+    /// 同一时间只处理一个事件, 按接收顺序处理
+    pub fn synchronize(self) -> Self {
+        self.delivery(DeliveryMode::PerListener)
+    }
+
+    pub fn concurrent(self) -> Self {
+        self.delivery(DeliveryMode::Concurrent)
     }
 
     pub fn set_priority(mut self, priority: Priority) -> Self {
         self.priority = priority;
         self
     }
+
+    /// 单次处理的最长时间(不包括按顺序等待之前事件的时间), 超时后处理将被取消, 未设置时使用全局默认值
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 添加过滤器, 多次调用时需全部匹配
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(match self.filter {
+            Some(f) => f.and(filter),
+            None => filter,
+        });
+        self
+    }
+
+    pub fn in_group(self, group_id: i64) -> Self {
+        self.filter(Filter::in_group(group_id))
+    }
+
+    pub fn from_user(self, uin: i64) -> Self {
+        self.filter(Filter::from_user(uin))
+    }
+
+    pub fn mentions_bot(self) -> Self {
+        self.filter(Filter::mentions_bot())
+    }
+
+    pub fn text_matches(self, regex: Regex) -> Self {
+        self.filter(Filter::text_matches(regex))
+    }
+
+    pub fn has_image(self) -> Self {
+        self.filter(Filter::has_image())
+    }
+
+    pub fn by_bot(self, bot_id: i64) -> Self {
+        self.filter(Filter::by_bot(bot_id))
+    }
+
+    /// 连续失败`max_failures`次后自动关闭监听器
+    pub fn circuit_breaker(mut self, max_failures: u32) -> Self {
+        self.max_failures = Some(max_failures.max(1));
+        self
+    }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Priority {
     Top = 0,
     High = 1,
@@ -181,6 +388,22 @@ pub enum Priority {
     Base = 4,
 }
 
+impl Priority {
+    pub(crate) const COUNT: usize = 5;
+}
+
+impl Display for Priority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Top => f.write_str("top"),
+            Self::High => f.write_str("high"),
+            Self::Middle => f.write_str("middle"),
+            Self::Low => f.write_str("low"),
+            Self::Base => f.write_str("base"),
+        }
+    }
+}
+
 impl Default for Priority {
     fn default() -> Self {
         Self::Middle
@@ -192,8 +415,10 @@ unsafe impl Send for Listener {}
 unsafe impl Sync for Listener {}
 
 pub struct ListenerGuard {
+    id: usize,
     name: Arc<String>,
     closed: Arc<AtomicBool>,
+    priority: Priority,
 }
 
 impl ListenerGuard {
@@ -201,6 +426,10 @@ impl ListenerGuard {
         &self.name
     }
 
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
     pub fn closed(&self) -> bool {
         self.closed.load(Ordering::Acquire)
     }
@@ -208,10 +437,15 @@ impl ListenerGuard {
 
 impl Drop for ListenerGuard {
     fn drop(&mut self) {
-        self.closed.swap(true, Ordering::Relaxed);
+        self.closed.store(true, Ordering::Release);
+        get_global_worker().unregister(self.id, self.priority);
     }
 }
 
+fn accepts<E: FromEvent>(event: &Event) -> bool {
+    E::from_event(event.clone()).is_some()
+}
+
 async fn bool_true() -> bool {
     true
 }
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::event::Event;
+use crate::service::listeners::{Dispatch, ListenerWorker};
+
+/// 事件处理的结果
+#[derive(Debug, Clone, Default)]
+pub struct Outcome {
+    /// 事件是否交给了监听器
+    pub delivered: bool,
+    pub intercepted: bool,
+    /// 处理此事件的监听器名称
+    pub handled_by: Option<String>,
+}
+
+/// 在所有监听器处理事件的前后运行, 按注册时的顺序从小到大调用;
+/// 同一处理器中, 之前的事件选出监听器前, 之后的事件无法交给监听器
+#[async_trait]
+pub trait Middleware: Send + Sync + 'static {
+    /// 调用`next.run(event)`继续处理, 不调用则事件不会交给监听器
+    async fn handle(&self, event: Event, next: Next<'_>) -> Outcome;
+}
+
+pub(crate) type Middlewares = Arc<Vec<(i32, Arc<dyn Middleware>)>>;
+
+/// 剩余的中间件与监听器
+pub struct Next<'a> {
+    worker: &'a ListenerWorker,
+    middlewares: &'a [(i32, Arc<dyn Middleware>)],
+    dispatch: Dispatch,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        worker: &'a ListenerWorker,
+        middlewares: &'a [(i32, Arc<dyn Middleware>)],
+        dispatch: Dispatch,
+    ) -> Self {
+        Self {
+            worker,
+            middlewares,
+            dispatch,
+        }
+    }
+
+    pub async fn run(self, event: Event) -> Outcome {
+        match self.middlewares.split_first() {
+            Some(((_, middleware), rest)) => {
+                let next = Next::new(self.worker, rest, self.dispatch);
+                middleware.handle(event, next).await
+            }
+            None => {
+                self.worker.deliver(&event, self.dispatch).await;
+
+                Outcome {
+                    delivered: true,
+                    intercepted: event.is_intercepted(),
+                    handled_by: event.handled_by(),
+                }
+            }
+        }
+    }
+}
@@ -1,26 +1,41 @@
 use std::mem::ManuallyDrop;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use ricq::handler::QEvent;
-use ricq::structs::GroupMessage;
+use ricq::structs::{FriendMessage, GroupMessage, TempMessage};
+use ricq::{RQError, RQResult};
 
 use atri_ffi::ffi::FFIEvent;
 use atri_ffi::Managed;
 use tokio::time::error::Elapsed;
 
+use crate::contact::friend::Friend;
 use crate::contact::group::Group;
+use crate::contact::member::TempMember;
 use crate::contact::{Contact, HasSubject};
+use crate::event::listener::current_listener;
+use crate::event::send::{MessagePostSendEvent, MessagePreSendEvent};
 use crate::{Bot, Listener, MessageChain};
 
+pub mod delivery;
+pub mod filter;
 pub mod listener;
+pub mod middleware;
+pub mod send;
+pub mod session;
+#[cfg(test)]
+pub(crate) mod testing;
 
 #[derive(Clone)]
 pub enum Event {
     BotOnlineEvent(BotOnlineEvent),
     GroupMessageEvent(GroupMessageEvent),
     FriendMessageEvent(FriendMessageEvent),
+    TempMessageEvent(TempMessageEvent),
+    MessagePreSendEvent(MessagePreSendEvent),
+    MessagePostSendEvent(MessagePostSendEvent),
     Unknown(EventInner<QEvent>),
 }
 
@@ -30,6 +45,9 @@ impl Event {
             Event::BotOnlineEvent(e) => (0, Managed::from_value(e)),
             Event::GroupMessageEvent(e) => (1, Managed::from_value(e)),
             Event::FriendMessageEvent(e) => (2, Managed::from_value(e)),
+            Event::TempMessageEvent(e) => (3, Managed::from_value(e)),
+            Event::MessagePreSendEvent(e) => (4, Managed::from_value(e)),
+            Event::MessagePostSendEvent(e) => (5, Managed::from_value(e)),
             Event::Unknown(e) => (255, Managed::from_value(e)),
         };
 
@@ -57,6 +75,9 @@ macro_rules! event_fun_impl {
         event_impl! {
             GroupMessageEvent,
             FriendMessageEvent,
+            TempMessageEvent,
+            MessagePreSendEvent,
+            MessagePostSendEvent,
             BotOnlineEvent,
             Unknown;
             $name: $ret as $func
@@ -68,6 +89,10 @@ macro_rules! event_fun_impl {
 event_fun_impl! {
     intercept: () as EventInner::intercept;
     is_intercepted: bool as EventInner::is_intercepted;
+    consume: () as EventInner::consume;
+    try_consume: bool as EventInner::try_consume;
+    is_consumed: bool as EventInner::is_consumed;
+    handled_by: Option<String> as EventInner::handled_by;
 }
 
 impl FromEvent for Event {
@@ -79,6 +104,7 @@ impl FromEvent for Event {
 #[derive(Debug)]
 pub struct EventInner<T> {
     intercepted: Arc<AtomicBool>,
+    handled_by: Arc<OnceLock<String>>,
     event: Arc<T>,
 }
 
@@ -86,10 +112,12 @@ impl<T> EventInner<T> {
     fn new(event: T) -> Self {
         Self {
             intercepted: AtomicBool::new(false).into(),
+            handled_by: OnceLock::new().into(),
             event: event.into(),
         }
     }
 
+    /// 阻止更低优先级的监听器处理此事件
     pub fn intercept(&self) {
         self.intercepted.swap(true, Ordering::Release);
     }
@@ -97,12 +125,38 @@ impl<T> EventInner<T> {
     pub fn is_intercepted(&self) -> bool {
         self.intercepted.load(Ordering::Relaxed)
     }
+
+    /// 声明此事件已被当前监听器处理, 尚未开始处理的同级监听器与更低优先级的监听器都不会再收到此事件;
+    /// 仅第一次调用成功, 之后返回`false`
+    pub fn try_consume(&self) -> bool {
+        let name = current_listener()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| String::from("Unknown"));
+
+        let consumed = self.handled_by.set(name).is_ok();
+        self.intercept();
+        consumed
+    }
+
+    pub fn consume(&self) {
+        self.try_consume();
+    }
+
+    pub fn is_consumed(&self) -> bool {
+        self.handled_by.get().is_some()
+    }
+
+    /// 处理此事件的监听器名称
+    pub fn handled_by(&self) -> Option<String> {
+        self.handled_by.get().cloned()
+    }
 }
 
 impl<T> Clone for EventInner<T> {
     fn clone(&self) -> Self {
         Self {
             intercepted: self.intercepted.clone(),
+            handled_by: self.handled_by.clone(),
             event: self.event.clone(),
         }
     }
@@ -205,7 +259,41 @@ impl FromEvent for GroupMessageEvent {
 
 pub type FriendMessageEvent = EventInner<imp::FriendMessageEvent>;
 
-impl FriendMessageEvent {}
+impl FriendMessageEvent {
+    pub fn from(bot: Bot, ori: ricq::client::event::FriendMessageEvent) -> Self {
+        Self::new(imp::FriendMessageEvent {
+            bot,
+            message: ori.inner,
+        })
+    }
+
+    pub fn bot(&self) -> &Bot {
+        &self.event.bot
+    }
+
+    pub fn message(&self) -> &FriendMessage {
+        &self.event.message
+    }
+
+    /// 发送者不是好友时返回`None`
+    pub async fn friend(&self) -> Option<Friend> {
+        self.bot().find_friend(self.message().from_uin).await
+    }
+}
+
+impl HasSubject for FriendMessageEvent {
+    fn subject(&self) -> Contact {
+        match self
+            .bot()
+            .friends()
+            .into_iter()
+            .find(|f| f.id() == self.message().from_uin)
+        {
+            Some(f) => Contact::Friend(f),
+            None => Contact::Stranger,
+        }
+    }
+}
 
 impl FromEvent for FriendMessageEvent {
     fn from_event(e: Event) -> Option<Self> {
@@ -217,12 +305,55 @@ impl FromEvent for FriendMessageEvent {
     }
 }
 
+/// 群临时会话消息
+pub type TempMessageEvent = EventInner<imp::TempMessageEvent>;
+
+impl TempMessageEvent {
+    pub fn from(bot: Bot, ori: ricq::client::event::TempMessageEvent) -> Self {
+        Self::new(imp::TempMessageEvent {
+            bot,
+            message: ori.inner,
+        })
+    }
+
+    pub fn bot(&self) -> &Bot {
+        &self.event.bot
+    }
+
+    pub fn message(&self) -> &TempMessage {
+        &self.event.message
+    }
+
+    /// 发送此消息的成员
+    pub fn sender(&self) -> TempMember {
+        TempMember::from(
+            self.bot().clone(),
+            self.message().group_code,
+            self.message().from_uin,
+        )
+    }
+}
+
+impl FromEvent for TempMessageEvent {
+    fn from_event(e: Event) -> Option<Self> {
+        if let Event::TempMessageEvent(e) = e {
+            Some(e)
+        } else {
+            None
+        }
+    }
+}
+
 pub type BotOnlineEvent = EventInner<imp::BotOnlineEvent>;
 
 impl BotOnlineEvent {
     pub fn from(bot: Bot) -> Self {
         Self::new(imp::BotOnlineEvent { bot })
     }
+
+    pub fn bot(&self) -> &Bot {
+        &self.event.bot
+    }
 }
 
 impl EventInner<QEvent> {
@@ -232,7 +363,7 @@ impl EventInner<QEvent> {
 }
 
 mod imp {
-    use ricq::structs::{FriendMessage, GroupMessage};
+    use ricq::structs::{FriendMessage, GroupMessage, TempMessage};
 
     use crate::contact::group::Group;
     use crate::Bot;
@@ -243,17 +374,78 @@ mod imp {
     }
 
     pub struct FriendMessageEvent {
+        pub bot: Bot,
         pub message: FriendMessage,
     }
 
+    pub struct TempMessageEvent {
+        pub bot: Bot,
+        pub message: TempMessage,
+    }
+
     pub struct BotOnlineEvent {
         pub bot: Bot,
     }
 }
 
+#[derive(Clone)]
 pub enum MessageEvent {
     Group(GroupMessageEvent),
     Friend(FriendMessageEvent),
+    Temp(TempMessageEvent),
+}
+
+impl MessageEvent {
+    pub fn bot(&self) -> &Bot {
+        match self {
+            Self::Group(e) => e.bot(),
+            Self::Friend(e) => e.bot(),
+            Self::Temp(e) => e.bot(),
+        }
+    }
+
+    /// 发送者的QQ号
+    pub fn sender(&self) -> i64 {
+        match self {
+            Self::Group(e) => e.message().from_uin,
+            Self::Friend(e) => e.message().from_uin,
+            Self::Temp(e) => e.message().from_uin,
+        }
+    }
+
+    pub fn elements(&self) -> &MessageChain {
+        match self {
+            Self::Group(e) => &e.message().elements,
+            Self::Friend(e) => &e.message().elements,
+            Self::Temp(e) => &e.message().elements,
+        }
+    }
+
+    /// 向消息来源(群, 好友或临时会话)发送消息
+    pub async fn reply(&self, chain: MessageChain) -> RQResult<()> {
+        match self {
+            Self::Group(e) => e.group().send_message(chain).await.map(|_| ()),
+            Self::Friend(e) => match e.friend().await {
+                Some(f) => f.send_message(chain).await.map(|_| ()),
+                None => Err(RQError::Other(format!(
+                    "{}不是{}的好友",
+                    e.message().from_uin,
+                    e.bot()
+                ))),
+            },
+            Self::Temp(e) => e.sender().send_message(chain).await,
+        }
+    }
+}
+
+impl From<MessageEvent> for Event {
+    fn from(e: MessageEvent) -> Self {
+        match e {
+            MessageEvent::Group(e) => Self::GroupMessageEvent(e),
+            MessageEvent::Friend(e) => Self::FriendMessageEvent(e),
+            MessageEvent::Temp(e) => Self::TempMessageEvent(e),
+        }
+    }
 }
 
 impl FromEvent for MessageEvent {
@@ -261,6 +453,7 @@ impl FromEvent for MessageEvent {
         match e {
             Event::GroupMessageEvent(e) => Some(Self::Group(e)),
             Event::FriendMessageEvent(e) => Some(Self::Friend(e)),
+            Event::TempMessageEvent(e) => Some(Self::Temp(e)),
             _ => None,
         }
     }
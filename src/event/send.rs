@@ -0,0 +1,244 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use ricq::structs::MessageReceipt;
+use ricq::{RQError, RQResult};
+
+use crate::contact::friend::Friend;
+use crate::contact::group::Group;
+use crate::contact::member::TempMember;
+use crate::event::{Event, EventInner, FromEvent};
+use crate::service::listeners::get_global_worker;
+use crate::{Bot, MessageChain};
+
+/// 消息的发送目标
+#[derive(Clone)]
+pub enum MessageTarget {
+    Group(Group),
+    Friend(Friend),
+    Temp(TempMember),
+}
+
+impl MessageTarget {
+    pub fn bot(&self) -> &Bot {
+        match self {
+            Self::Group(g) => g.bot(),
+            Self::Friend(f) => f.bot(),
+            Self::Temp(m) => m.bot(),
+        }
+    }
+
+    /// 群号或QQ号
+    pub fn id(&self) -> i64 {
+        match self {
+            Self::Group(g) => g.id(),
+            Self::Friend(f) => f.id(),
+            Self::Temp(m) => m.id(),
+        }
+    }
+}
+
+/// 消息发送前触发, 监听器可修改或取消将要发送的消息
+pub type MessagePreSendEvent = EventInner<imp::MessagePreSendEvent>;
+
+impl MessagePreSendEvent {
+    fn from(target: MessageTarget, message: MessageChain) -> Self {
+        Self::new(imp::MessagePreSendEvent {
+            target,
+            message: Mutex::new(message),
+            cancelled: AtomicBool::new(false),
+        })
+    }
+
+    pub fn target(&self) -> &MessageTarget {
+        &self.event.target
+    }
+
+    pub fn bot(&self) -> &Bot {
+        self.target().bot()
+    }
+
+    pub fn message(&self) -> MessageChain {
+        self.event
+            .message
+            .lock()
+            .expect("Cannot lock message")
+            .clone()
+    }
+
+    pub fn set_message(&self, message: MessageChain) {
+        *self.event.message.lock().expect("Cannot lock message") = message;
+    }
+
+    /// 取消发送, 发送方将收到错误, 并触发带有错误的[`MessagePostSendEvent`]
+    pub fn cancel(&self) {
+        self.event.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.event.cancelled.load(Ordering::Acquire)
+    }
+}
+
+impl FromEvent for MessagePreSendEvent {
+    fn from_event(e: Event) -> Option<Self> {
+        if let Event::MessagePreSendEvent(e) = e {
+            Some(e)
+        } else {
+            None
+        }
+    }
+}
+
+/// 消息发送后触发, 包含发送回执或错误
+pub type MessagePostSendEvent = EventInner<imp::MessagePostSendEvent>;
+
+impl MessagePostSendEvent {
+    pub fn target(&self) -> &MessageTarget {
+        &self.event.target
+    }
+
+    pub fn bot(&self) -> &Bot {
+        self.target().bot()
+    }
+
+    /// 实际发送的消息
+    pub fn message(&self) -> &MessageChain {
+        &self.event.message
+    }
+
+    pub fn receipt(&self) -> Option<&MessageReceipt> {
+        self.event.receipt.as_ref()
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.event.error.as_deref()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.event.error.is_none()
+    }
+}
+
+impl FromEvent for MessagePostSendEvent {
+    fn from_event(e: Event) -> Option<Self> {
+        if let Event::MessagePostSendEvent(e) = e {
+            Some(e)
+        } else {
+            None
+        }
+    }
+}
+
+/// 触发[`MessagePreSendEvent`]并等待监听器处理, 返回修改后的消息;
+/// 消息被取消时触发带有错误的[`MessagePostSendEvent`]并返回错误;
+/// 在监听器中发送消息时, 该监听器不会收到自己发送的消息的此事件;
+/// 此事件不为顺序处理的监听器排队, 以免在顺序处理的监听器中发送消息时互相等待
+pub(crate) async fn pre_send(
+    target: &MessageTarget,
+    message: MessageChain,
+) -> RQResult<MessageChain> {
+    let event = MessagePreSendEvent::from(target.clone(), message);
+    get_global_worker()
+        .handle(Event::MessagePreSendEvent(event.clone()))
+        .await;
+
+    if event.is_cancelled() {
+        let err = RQError::Other(format!(
+            "{}发送至{}的消息已被取消",
+            target.bot(),
+            target.id()
+        ));
+        // 被取消的消息同样触发发送后事件, 以便监听器得知发送结果
+        post_send(target.clone(), event.message(), Err(&err));
+        return Err(err);
+    }
+
+    Ok(event.message())
+}
+
+/// 触发[`MessagePostSendEvent`], 不等待监听器处理
+pub(crate) fn post_send(
+    target: MessageTarget,
+    message: MessageChain,
+    result: Result<Option<&MessageReceipt>, &RQError>,
+) {
+    let (receipt, error) = match result {
+        Ok(receipt) => (receipt.cloned(), None),
+        Err(e) => (None, Some(format!("{:?}", e))),
+    };
+
+    let event = MessagePostSendEvent::new(imp::MessagePostSendEvent {
+        target,
+        message,
+        receipt,
+        error,
+    });
+    get_global_worker().dispatch(Event::MessagePostSendEvent(event));
+}
+
+mod imp {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Mutex;
+
+    use ricq::structs::MessageReceipt;
+
+    use super::MessageTarget;
+    use crate::MessageChain;
+
+    pub struct MessagePreSendEvent {
+        pub target: MessageTarget,
+        pub message: Mutex<MessageChain>,
+        pub cancelled: AtomicBool,
+    }
+
+    pub struct MessagePostSendEvent {
+        pub target: MessageTarget,
+        pub message: MessageChain,
+        pub receipt: Option<MessageReceipt>,
+        pub error: Option<String>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{MessagePostSendEvent, MessagePreSendEvent};
+    use crate::contact::member::TempMember;
+    use crate::event::testing::{bot, text};
+    use crate::Listener;
+
+    #[tokio::test]
+    async fn cancelled_send_fires_post_send() {
+        let bot = bot(50001).await;
+        let _cancel = Listener::listening_on(|e: MessagePreSendEvent| async move {
+            e.cancel();
+            true
+        })
+        .by_bot(bot.id())
+        .start();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let _record = Listener::listening_on(move |e: MessagePostSendEvent| {
+            let tx = tx.clone();
+            async move {
+                tx.send((e.message().clone(), e.error().map(str::to_owned)))
+                    .ok();
+                true
+            }
+        })
+        .by_bot(bot.id())
+        .start();
+
+        let member = TempMember::from(bot, 1, 2);
+        assert!(member.send_message(text("hello")).await.is_err());
+
+        let (message, error) = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.to_string(), "hello");
+        assert!(error.unwrap().contains("已被取消"));
+    }
+}
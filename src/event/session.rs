@@ -0,0 +1,376 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use ricq::RQError;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::event::filter::Filter;
+use crate::event::middleware::{Middleware, Next, Outcome};
+use crate::event::{Event, MessageEvent};
+use crate::MessageChain;
+
+/// 会话所在的聊天
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SessionContact {
+    Group(i64),
+    Friend,
+    /// 来自指定群的临时会话
+    Temp(i64),
+}
+
+/// 会话的唯一标识, 同一时间每个标识只能存在一个会话
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionKey {
+    pub bot: i64,
+    pub contact: SessionContact,
+    pub user: i64,
+}
+
+impl SessionKey {
+    pub fn of(event: &MessageEvent) -> Self {
+        let contact = match event {
+            MessageEvent::Group(e) => SessionContact::Group(e.group().id()),
+            MessageEvent::Friend(_) => SessionContact::Friend,
+            MessageEvent::Temp(e) => SessionContact::Temp(e.message().group_code),
+        };
+
+        Self {
+            bot: event.bot().id(),
+            contact,
+            user: event.sender(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    /// 该用户已有进行中的会话
+    Busy,
+    Timeout,
+    Cancelled,
+    Send(RQError),
+}
+
+impl Display for SessionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Busy => f.write_str("Session already exists"),
+            Self::Timeout => f.write_str("Session timed out"),
+            Self::Cancelled => f.write_str("Session cancelled"),
+            Self::Send(e) => write!(f, "Cannot send message: {:?}", e),
+        }
+    }
+}
+
+impl Error for SessionError {}
+
+struct SessionEntry {
+    id: u64,
+    tx: mpsc::Sender<MessageEvent>,
+}
+
+fn sessions() -> &'static DashMap<SessionKey, SessionEntry> {
+    static SESSIONS: OnceLock<DashMap<SessionKey, SessionEntry>> = OnceLock::new();
+    SESSIONS.get_or_init(DashMap::new)
+}
+
+/// 会话期间该用户在该聊天中的消息只会交给会话, 不会触发其他监听器
+pub struct Session {
+    id: u64,
+    key: SessionKey,
+    origin: MessageEvent,
+    rx: mpsc::Receiver<MessageEvent>,
+}
+
+impl Session {
+    /// 以此消息的发送者与来源开启会话
+    pub fn start(event: &MessageEvent) -> Result<Self, SessionError> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let key = SessionKey::of(event);
+        let (tx, rx) = mpsc::channel(16);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+        match sessions().entry(key) {
+            Entry::Occupied(mut entry) => {
+                if !entry.get().tx.is_closed() {
+                    return Err(SessionError::Busy);
+                }
+                entry.insert(SessionEntry { id, tx });
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(SessionEntry { id, tx });
+            }
+        }
+
+        Ok(Self {
+            id,
+            key,
+            origin: event.clone(),
+            rx,
+        })
+    }
+
+    pub fn key(&self) -> &SessionKey {
+        &self.key
+    }
+
+    /// 开启会话的消息
+    pub fn origin(&self) -> &MessageEvent {
+        &self.origin
+    }
+
+    pub async fn reply(&self, chain: MessageChain) -> Result<(), SessionError> {
+        self.origin.reply(chain).await.map_err(SessionError::Send)
+    }
+
+    /// 等待用户的下一条消息
+    pub async fn next(&mut self, timeout: Duration) -> Result<MessageEvent, SessionError> {
+        match tokio::time::timeout(timeout, self.rx.recv()).await {
+            Ok(Some(e)) => Ok(e),
+            Ok(None) => Err(SessionError::Cancelled),
+            Err(_) => Err(SessionError::Timeout),
+        }
+    }
+
+    /// 等待匹配过滤器的消息, 期间不匹配的消息将被丢弃
+    pub async fn expect(
+        &mut self,
+        filter: Filter,
+        timeout: Duration,
+    ) -> Result<MessageEvent, SessionError> {
+        tokio::time::timeout(timeout, async {
+            while let Some(e) = self.rx.recv().await {
+                if filter.matches(&Event::from(e.clone())) {
+                    return Ok(e);
+                }
+            }
+
+            Err(SessionError::Cancelled)
+        })
+        .await
+        .unwrap_or(Err(SessionError::Timeout))
+    }
+
+    /// 发送提示并等待用户回复
+    pub async fn ask(
+        &mut self,
+        prompt: MessageChain,
+        timeout: Duration,
+    ) -> Result<MessageEvent, SessionError> {
+        self.reply(prompt).await?;
+        self.next(timeout).await
+    }
+
+    /// 结束会话, 之后的消息将正常交给监听器
+    pub fn cancel(self) {}
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        sessions().remove_if(&self.key, |_, entry| entry.id == self.id);
+    }
+}
+
+/// 从外部取消会话, 等待中的`next`, `expect`与`ask`将返回[`SessionError::Cancelled`]
+pub fn cancel_session(key: &SessionKey) -> bool {
+    sessions().remove(key).is_some()
+}
+
+/// 若事件属于进行中的会话, 将其交给会话并返回`true`; 会话的消息队列已满时返回`false`
+pub(crate) fn route(event: &Event) -> bool {
+    let event = match event {
+        Event::GroupMessageEvent(e) => MessageEvent::Group(e.clone()),
+        Event::FriendMessageEvent(e) => MessageEvent::Friend(e.clone()),
+        Event::TempMessageEvent(e) => MessageEvent::Temp(e.clone()),
+        _ => return false,
+    };
+
+    let key = SessionKey::of(&event);
+    let entry = match sessions().get(&key) {
+        Some(entry) => entry,
+        None => return false,
+    };
+
+    match entry.tx.try_send(event) {
+        Ok(()) => true,
+        // 会话处理不及时, 消息交给监听器而不是丢弃
+        Err(mpsc::error::TrySendError::Full(_)) => {
+            warn!("会话{:?}的消息过多, 此消息将交给监听器", key);
+            false
+        }
+        Err(mpsc::error::TrySendError::Closed(_)) => {
+            drop(entry);
+            sessions().remove_if(&key, |_, entry| entry.tx.is_closed());
+            false
+        }
+    }
+}
+
+/// 在其他中间件之后将属于会话的消息交给会话, 这些消息不会再交给监听器
+pub(crate) struct SessionRouter;
+
+#[async_trait]
+impl Middleware for SessionRouter {
+    async fn handle(&self, event: Event, next: Next<'_>) -> Outcome {
+        if route(&event) {
+            return Outcome::default();
+        }
+
+        next.run(event).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use regex::Regex;
+
+    use super::{cancel_session, route, Session, SessionContact, SessionError, SessionKey};
+    use crate::event::filter::Filter;
+    use crate::event::testing::{bot, friend_message, group_message, temp_message, text, unknown};
+    use crate::event::{Event, FromEvent, MessageEvent};
+
+    const WAIT: Duration = Duration::from_millis(100);
+
+    fn message(event: Event) -> MessageEvent {
+        MessageEvent::from_event(event).expect("not a message event")
+    }
+
+    fn text_of(event: &MessageEvent) -> String {
+        event.elements().to_string()
+    }
+
+    #[tokio::test]
+    async fn key_of_each_contact() {
+        let bot = bot(20001).await;
+
+        assert_eq!(
+            SessionKey::of(&message(group_message(&bot, 1, 2, text("hi")))),
+            SessionKey {
+                bot: 20001,
+                contact: SessionContact::Group(1),
+                user: 2,
+            }
+        );
+        assert_eq!(
+            SessionKey::of(&message(friend_message(&bot, 2, text("hi")))),
+            SessionKey {
+                bot: 20001,
+                contact: SessionContact::Friend,
+                user: 2,
+            }
+        );
+        assert_eq!(
+            SessionKey::of(&message(temp_message(&bot, 1, 2, text("hi")))),
+            SessionKey {
+                bot: 20001,
+                contact: SessionContact::Temp(1),
+                user: 2,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn routes_only_the_session_owner() {
+        let bot = bot(20002).await;
+        let mut session =
+            Session::start(&message(group_message(&bot, 1, 2, text("start")))).unwrap();
+
+        assert!(route(&group_message(&bot, 1, 2, text("mine"))));
+        // 其他用户, 其他群, 同一用户的私聊与非消息事件都不属于此会话
+        assert!(!route(&group_message(&bot, 1, 3, text("other user"))));
+        assert!(!route(&group_message(&bot, 4, 2, text("other group"))));
+        assert!(!route(&friend_message(&bot, 2, text("friend"))));
+        assert!(!route(&temp_message(&bot, 1, 2, text("temp"))));
+        assert!(!route(&unknown()));
+
+        assert_eq!(text_of(&session.next(WAIT).await.unwrap()), "mine");
+        assert!(matches!(
+            session.next(WAIT).await,
+            Err(SessionError::Timeout)
+        ));
+    }
+
+    #[tokio::test]
+    async fn one_session_per_key() {
+        let bot = bot(20003).await;
+        let origin = message(friend_message(&bot, 2, text("start")));
+
+        let session = Session::start(&origin).unwrap();
+        assert!(matches!(Session::start(&origin), Err(SessionError::Busy)));
+        // 其他用户不受影响
+        let other = Session::start(&message(friend_message(&bot, 3, text("start")))).unwrap();
+
+        session.cancel();
+        assert!(!route(&friend_message(&bot, 2, text("after"))));
+        assert!(Session::start(&origin).is_ok());
+        drop(other);
+    }
+
+    #[tokio::test]
+    async fn cancelled_from_outside() {
+        let bot = bot(20004).await;
+        let mut session = Session::start(&message(friend_message(&bot, 2, text("start")))).unwrap();
+
+        assert!(cancel_session(session.key()));
+        assert!(matches!(
+            session.next(WAIT).await,
+            Err(SessionError::Cancelled)
+        ));
+        assert!(!route(&friend_message(&bot, 2, text("after"))));
+        assert!(!cancel_session(session.key()));
+    }
+
+    #[tokio::test]
+    async fn expect_skips_other_messages() {
+        let bot = bot(20005).await;
+        let mut session = Session::start(&message(friend_message(&bot, 2, text("start")))).unwrap();
+
+        assert!(route(&friend_message(&bot, 2, text("maybe"))));
+        assert!(route(&friend_message(&bot, 2, text("yes"))));
+
+        let filter = Filter::text_matches(Regex::new("^(yes|no)$").unwrap());
+        assert_eq!(
+            text_of(&session.expect(filter.clone(), WAIT).await.unwrap()),
+            "yes"
+        );
+        assert!(matches!(
+            session.expect(filter, WAIT).await,
+            Err(SessionError::Timeout)
+        ));
+    }
+
+    #[tokio::test]
+    async fn full_session_falls_through() {
+        let bot = bot(20006).await;
+        let mut session = Session::start(&message(friend_message(&bot, 2, text("start")))).unwrap();
+
+        for i in 0..16 {
+            assert!(route(&friend_message(&bot, 2, text(&i.to_string()))));
+        }
+        assert!(!route(&friend_message(&bot, 2, text("overflow"))));
+
+        assert_eq!(text_of(&session.next(WAIT).await.unwrap()), "0");
+        assert!(route(&friend_message(&bot, 2, text("16"))));
+    }
+
+    #[tokio::test]
+    async fn dropped_session_falls_through() {
+        let bot = bot(20007).await;
+        let origin = message(temp_message(&bot, 1, 2, text("start")));
+        let key = SessionKey::of(&origin);
+
+        drop(Session::start(&origin).unwrap());
+        assert!(!route(&temp_message(&bot, 1, 2, text("after"))));
+        assert!(!cancel_session(&key));
+    }
+}
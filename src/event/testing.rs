@@ -0,0 +1,97 @@
+//! 在测试中构造事件, Bot不会连接服务器
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ricq::handler::QEvent;
+use ricq::msg::elem::Text;
+use ricq::structs::{FriendMessage, GroupInfo, GroupMessage, TempMessage};
+
+use crate::bot::BotConfiguration;
+use crate::config::login::Protocol;
+use crate::contact::group::Group;
+use crate::event::{imp, Event, EventInner};
+use crate::{Bot, MessageChain};
+
+/// 每次调用使用单独的工作目录, 同时运行的测试不会读写同一个设备文件
+pub(crate) async fn bot(id: i64) -> Bot {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let work_dir = std::env::temp_dir().join(format!(
+        "atri_test_bot_{}_{}_{}",
+        id,
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    Bot::new(
+        id,
+        BotConfiguration {
+            work_dir: Some(work_dir),
+            version: Protocol::IPAD.as_version(),
+            servers: vec![],
+            proxy: None,
+        },
+    )
+    .await
+}
+
+pub(crate) fn text(s: &str) -> MessageChain {
+    MessageChain::new(Text::new(s.to_owned()))
+}
+
+pub(crate) fn group_message(
+    bot: &Bot,
+    group_code: i64,
+    from_uin: i64,
+    elements: MessageChain,
+) -> Event {
+    let group = Group::from(
+        bot.clone(),
+        GroupInfo {
+            code: group_code,
+            ..Default::default()
+        },
+    );
+
+    Event::GroupMessageEvent(EventInner::new(imp::GroupMessageEvent {
+        group,
+        message: GroupMessage {
+            group_code,
+            from_uin,
+            elements,
+            ..Default::default()
+        },
+    }))
+}
+
+pub(crate) fn friend_message(bot: &Bot, from_uin: i64, elements: MessageChain) -> Event {
+    Event::FriendMessageEvent(EventInner::new(imp::FriendMessageEvent {
+        bot: bot.clone(),
+        message: FriendMessage {
+            from_uin,
+            elements,
+            ..Default::default()
+        },
+    }))
+}
+
+pub(crate) fn temp_message(
+    bot: &Bot,
+    group_code: i64,
+    from_uin: i64,
+    elements: MessageChain,
+) -> Event {
+    Event::TempMessageEvent(EventInner::new(imp::TempMessageEvent {
+        bot: bot.clone(),
+        message: TempMessage {
+            group_code,
+            from_uin,
+            elements,
+            ..Default::default()
+        },
+    }))
+}
+
+/// 不属于任何Bot的事件
+pub(crate) fn unknown() -> Event {
+    Event::Unknown(EventInner::from(QEvent::Login(0)))
+}
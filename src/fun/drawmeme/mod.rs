@@ -47,7 +47,7 @@ pub fn drawmeme_listener() -> ListenerGuard {
 
             if let Some(cap) = find {
                 let num = unwrap_result_or_print_err_return!(u8::from_str(&cap[1]));
-                if num > 100 {
+                if num > 100 || !e.try_consume() {
                     return;
                 }
 
@@ -1,16 +1,18 @@
 mod data;
 
-use dashmap::DashSet;
 use rand::Rng;
 use std::error::Error;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
+use crate::event::filter::Filter;
 use crate::event::listener::ListenerGuard;
-use crate::event::GroupMessageEvent;
+use crate::event::session::Session;
+use crate::event::{GroupMessageEvent, MessageEvent};
 use crate::{get_app, Listener};
-use ricq::msg::elem::{RQElem, Reply};
+use regex::Regex;
+use ricq::msg::elem::Reply;
 use ricq::msg::MessageChainBuilder;
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
@@ -26,32 +28,18 @@ pub fn moli_listener() -> ListenerGuard {
     serivce.with_path(path);
 
     let config: MoliConfig = serivce.read_config();
+    // 每轮对话最多等待10秒, 另需预留请求接口的时间
+    let timeout = Duration::from_secs(30 * (config.reply_times as u64 + 1));
+    let called = Filter::mentions_bot().or(Filter::text_matches(
+        Regex::new(&regex::escape(&config.name)).expect("Cannot parse regex"),
+    ));
     let cfg = Arc::new(config);
 
-    let set = DashSet::<i64>::new();
-    let set = Arc::new(set);
-
     Listener::listening_on_always(move |e: GroupMessageEvent| {
         let config = cfg.clone();
 
-        let set = set.clone();
         async move {
-            if set.contains(&e.message().from_uin) {
-                return;
-            }
-            let f = || {
-                let msg = e.message().clone();
-                for elem in msg.elements {
-                    match elem {
-                        RQElem::At(at) if at.target == e.bot().id() => return true,
-                        RQElem::Text(s) if s.content.contains(&config.name) => return true,
-                        _ => {}
-                    }
-                }
-                false
-            };
-
-            if !f() {
+            if !e.try_consume() {
                 return;
             }
 
@@ -127,8 +115,11 @@ pub fn moli_listener() -> ListenerGuard {
                 Ok(())
             }
 
-            let sender = e.message().from_uin;
-            set.insert(sender);
+            // 对话期间该成员的消息只会交给此会话
+            let mut session = match Session::start(&MessageEvent::Group(e.clone())) {
+                Ok(session) => session,
+                Err(_) => return,
+            };
 
             let mut e = e;
 
@@ -137,29 +128,28 @@ pub fn moli_listener() -> ListenerGuard {
             }
 
             for _ in 0..config.reply_times {
-                e = if let Ok(e) = e
-                    .next_event(Duration::from_secs(10), |e| e.message().from_uin == sender)
-                    .await
-                {
-                    e
-                } else {
-                    let mut msg = MessageChainBuilder::new();
-                    let random = rand::thread_rng().gen_range(0..config.timeout_reply.len());
-                    msg.push_str(&config.timeout_reply[random]);
-                    let _ = e.group().send_message(msg.build()).await;
-
-                    set.remove(&sender);
-                    return;
+                e = match session.next(Duration::from_secs(10)).await {
+                    Ok(MessageEvent::Group(e)) => e,
+                    Ok(_) => continue,
+                    Err(_) => {
+                        let mut msg = MessageChainBuilder::new();
+                        let random = rand::thread_rng().gen_range(0..config.timeout_reply.len());
+                        msg.push_str(&config.timeout_reply[random]);
+                        let _ = e.group().send_message(msg.build()).await;
+
+                        return;
+                    }
                 };
 
                 if let Err(e) = handle_message(&e, &config).await {
                     error!("Moli: Error on handle message {}", e);
                 }
             }
-            set.remove(&sender);
         }
     })
     .with_name("Moli-Chat")
+    .filter(called)
+    .timeout(timeout)
     .start()
 }
 
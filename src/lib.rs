@@ -8,15 +8,15 @@ use ricq::msg::elem::Text;
 use ricq::msg::MessageChain;
 use ricq::structs::GroupMemberInfo;
 
-use tokio::runtime;
-use tokio::runtime::Runtime;
-
+use crate::bot::status::{format_elapsed, AppStatus, BotStatus};
 use crate::bot::Bot;
 
 use crate::event::listener::Listener;
 use crate::event::Event;
-use crate::service::listeners::ListenerWorker;
+use crate::service::http;
 use crate::service::plugin::PluginManager;
+use crate::service::routing::{self, GroupAssignments, GroupRouter};
+use crate::service::runtime::{get_runtime_of, AtriRuntime, RuntimeKind};
 
 pub mod bot;
 pub mod channel;
@@ -31,33 +31,12 @@ pub mod plugin;
 pub mod service;
 
 pub struct Atri {
-    global_runtime: Runtime,
-    //listener_runtime: Runtime,
-    //listener_worker: ListenerWorker,
     plugin_manager: PluginManager,
 }
 
 impl Atri {
     pub fn new() -> Self {
-        let global_runtime = runtime::Builder::new_multi_thread()
-            .thread_name("GlobalRuntime")
-            .enable_all()
-            .build()
-            .unwrap();
-
-        let listener_runtime = runtime::Builder::new_multi_thread()
-            .worker_threads(8)
-            .thread_name("Listeners")
-            .enable_all()
-            .build()
-            .unwrap();
-
-        let listener_worker = ListenerWorker::new();
-
         Self {
-            global_runtime,
-            //listener_runtime,
-            //listener_worker,
             plugin_manager: PluginManager::new(),
         }
     }
@@ -73,45 +52,30 @@ pub fn get_app() -> &'static App {
     APP.get_or_init(App::new)
 }
 
-static ASYNC_RUNTIME: OnceLock<Runtime> = OnceLock::new();
-
-pub fn get_runtime() -> &'static Runtime {
-    ASYNC_RUNTIME.get_or_init(|| {
-        runtime::Builder::new_multi_thread()
-            .thread_name("GlobalRuntime")
-            .enable_all()
-            .build()
-            .unwrap()
-    })
+pub fn get_runtime() -> &'static AtriRuntime {
+    get_runtime_of(RuntimeKind::Global)
 }
 
-static LISTENER_RUNTIME: OnceLock<Runtime> = OnceLock::new();
-
-pub fn get_listener_runtime() -> &'static Runtime {
-    LISTENER_RUNTIME.get_or_init(|| {
-        runtime::Builder::new_multi_thread()
-            .worker_threads(8)
-            .thread_name("Listeners")
-            .enable_all()
-            .build()
-            .unwrap()
-    })
+pub fn get_listener_runtime() -> &'static AtriRuntime {
+    get_runtime_of(RuntimeKind::Listener)
 }
 
 pub struct App {
     bots: DashMap<i64, Bot>,
-    group_bot: DashMap<i64, i64>,
+    group_bot: GroupAssignments,
     group_members_info: DashMap<i64, Arc<GroupMemberInfo>>,
     http_client: reqwest::Client,
+    router: Box<dyn GroupRouter>,
 }
 
 impl App {
     pub fn new() -> Self {
         Self {
             bots: DashMap::new(),
-            group_bot: DashMap::new(),
+            group_bot: GroupAssignments::load(),
             group_members_info: DashMap::new(),
-            http_client: reqwest::Client::new(),
+            http_client: http::build_http_client(),
+            router: routing::load_router(),
         }
     }
 
@@ -125,26 +89,83 @@ impl App {
         bots
     }
 
+    pub fn bot(&self, id: i64) -> Option<Bot> {
+        self.bots.get(&id).map(|b| b.clone())
+    }
+
     pub fn group_bot(&self, group_id: i64) -> Option<i64> {
-        self.group_bot.get(&group_id).map(|r| *r.value())
+        self.group_bot.get(group_id)
     }
 
     pub fn set_group_bot(&self, group_id: i64, bot_id: i64) -> Option<i64> {
-        self.group_bot.insert(group_id, bot_id)
+        self.group_bot.set(group_id, bot_id)
     }
 
+    /// 此Bot是否负责回复该群, 多个Bot同时处理同一个群时只有一个返回`true`
     pub fn check_group_bot(&self, bot_id: i64, group_id: i64) -> bool {
-        let group_bot = self.group_bot(group_id);
-
-        if let Some(id) = group_bot {
-            if id != bot_id {
-                return false;
+        let current = self.group_bot(group_id);
+        if let Some(id) = current {
+            if id == bot_id || self.is_bot_online(id) {
+                return id == bot_id;
             }
-        } else {
-            get_app().set_group_bot(group_id, bot_id);
         }
 
-        true
+        let mut candidates = vec![bot_id];
+        candidates.extend(
+            self.group_candidates(group_id)
+                .into_iter()
+                .filter(|id| *id != bot_id),
+        );
+
+        match self.router.route(&self.group_bot, group_id, &candidates) {
+            Some(id) => self.group_bot.assign(group_id, current, id) == bot_id,
+            None => false,
+        }
+    }
+
+    pub fn assigned_group_count(&self, bot_id: i64) -> usize {
+        self.group_bot.assigned_count(bot_id)
+    }
+
+    /// 写入未保存的群分配信息
+    pub(crate) fn save_group_assignments(&self) {
+        self.group_bot.flush();
+    }
+
+    fn is_bot_online(&self, bot_id: i64) -> bool {
+        self.bots
+            .get(&bot_id)
+            .map(|b| b.is_online())
+            .unwrap_or(false)
+    }
+
+    fn group_candidates(&self, group_id: i64) -> Vec<i64> {
+        self.bots
+            .iter()
+            .filter(|b| b.is_online() && b.has_group(group_id))
+            .map(|b| b.id())
+            .collect()
+    }
+
+    fn reroute(&self, group_id: i64) {
+        let candidates = self.group_candidates(group_id);
+        routing::reroute(&*self.router, &self.group_bot, group_id, &candidates);
+    }
+
+    pub(crate) fn on_bot_online(&self, bot: &Bot) {
+        if !self.router.reroute_on_online() {
+            return;
+        }
+
+        for group in bot.groups() {
+            self.reroute(group.id());
+        }
+    }
+
+    pub(crate) fn on_bot_offline(&self, bot_id: i64) {
+        for group_id in self.group_bot.groups_of(bot_id) {
+            self.reroute(group_id);
+        }
     }
 
     pub(crate) fn add_bot(&self, bot: Bot) -> Option<Bot> {
@@ -152,7 +173,18 @@ impl App {
     }
 
     pub(crate) fn remove_bot(&self, bot: i64) -> Option<Bot> {
-        self.bots.remove(&bot).map(|(_, bot)| bot)
+        let removed = self.bots.remove(&bot).map(|(_, bot)| bot);
+        if removed.is_some() {
+            self.on_bot_offline(bot);
+        }
+        removed
+    }
+
+    pub fn status(&self) -> AppStatus {
+        let mut bots: Vec<BotStatus> = self.bots.iter().map(|b| b.status()).collect();
+        bots.sort_by_key(|s| s.id);
+
+        bots.into()
     }
 
     pub fn http_client(&self) -> &reqwest::Client {
@@ -169,16 +201,29 @@ pub fn main_handler() {
                 let s = e.message().elements.to_string();
                 match &*s {
                     "萝卜子列表" => {
+                        if !e.try_consume() {
+                            return;
+                        }
+
                         let app = get_app();
-                        let bots = &app.bots;
+                        let status = app.status();
+
+                        let mut s =
+                            format!("在线的萝卜子({}/{})\n", status.online, status.bots.len());
+                        for bot in status.bots.iter().filter(|b| b.is_online()) {
+                            let nickname = match app.bot(bot.id) {
+                                Some(b) => b.nickname().await,
+                                None => continue,
+                            };
 
-                        let mut s = String::from("在线的萝卜子\n");
-                        for bot in bots.iter() {
                             s.push_str(&format!(
-                                "{0}: {1}",
-                                bot.client().account_info.read().await.nickname,
-                                bot.client().uin().await
+                                "{0}: {1} (收{2}/发{3}",
+                                nickname, bot.id, bot.messages_received, bot.messages_sent,
                             ));
+                            if let Some(time) = bot.login_time {
+                                s.push_str(&format!(", 在线{}", format_elapsed(time)));
+                            }
+                            s.push(')');
                             s.push('\n');
                         }
                         s.pop();
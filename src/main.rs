@@ -1,17 +1,17 @@
 extern crate core;
 
 use std::error::Error;
-use std::mem;
 
-use tokio::io;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-
-use atri_qq::event::listener::{Listener, Priority};
-use atri_qq::event::GroupMessageEvent;
+use async_trait::async_trait;
+use atri_qq::event::middleware::{Middleware, Next, Outcome};
+use atri_qq::event::Event;
+use atri_qq::service::command::console::ConsoleReader;
+use atri_qq::service::command::{dispatch_line, exit_requested, register_builtin_commands};
 use atri_qq::service::listeners::get_global_worker;
 use atri_qq::service::log::init_logger;
 use atri_qq::service::login::login_bots;
-use atri_qq::{fun, get_app, get_listener_runtime, get_runtime, main_handler, Atri};
+use atri_qq::service::shutdown::{shutdown, wait_for_signal};
+use atri_qq::{fun, get_app, get_runtime, main_handler, Atri};
 
 type MainResult = Result<(), Box<dyn Error>>;
 
@@ -20,33 +20,49 @@ fn main() -> MainResult {
 
     init_logger();
 
-    get_listener_runtime().spawn(get_global_worker().start());
-
     atri.load_plugins()?;
 
     let runtime = get_runtime();
 
-    let guard = Listener::listening_on_always(|e: GroupMessageEvent| async move {
-        if !get_app().check_group_bot(e.group().bot().id(), e.group().id()) {
-            e.intercept();
-        }
-    })
-    .set_priority(Priority::Top)
-    .start();
-    mem::forget(guard);
+    get_global_worker().add_middleware(0, CheckGroupBot);
 
     main_handler();
     fun::handler();
+    register_builtin_commands();
 
     runtime.spawn(async {
         main0().await.expect("Error");
     });
 
-    runtime.block_on(loop_cli())?;
+    runtime.block_on(async {
+        tokio::select! {
+            result = loop_cli() => result?,
+            _ = wait_for_signal() => {}
+        }
+
+        shutdown(&atri).await;
+        MainResult::Ok(())
+    })?;
 
     Ok(())
 }
 
+/// 群消息只交给负责该群的Bot处理
+struct CheckGroupBot;
+
+#[async_trait]
+impl Middleware for CheckGroupBot {
+    async fn handle(&self, event: Event, next: Next<'_>) -> Outcome {
+        if let Event::GroupMessageEvent(ref e) = event {
+            if !get_app().check_group_bot(e.bot().id(), e.group().id()) {
+                return Outcome::default();
+            }
+        }
+
+        next.run(event).await
+    }
+}
+
 async fn main0() -> MainResult {
     login_bots().await?;
 
@@ -54,39 +70,14 @@ async fn main0() -> MainResult {
 }
 
 async fn loop_cli() -> MainResult {
-    let stdin = io::stdin();
-    let mut stdin = BufReader::new(stdin);
-    let mut stdout = io::stdout();
-
-    loop {
-        let mut buf = String::new();
-        stdin.read_line(&mut buf).await?;
-        let cmd = buf.trim_end();
-
-        match cmd {
-            "" => {
-                // nothing to do
-            }
-            "help" | "?" => {
-                static HELP_INFO: &str = "\
-help: Show this info
-exit: Exit this program
-";
-                stdout.write_all(HELP_INFO.as_bytes()).await?;
-            }
-            "exit" | "quit" | "stop" => {
-                println!("Stopping...");
-                break;
-            }
-            _ => {
-                println!(
-                    "Unknown command '{}', use 'help' to show the help info",
-                    cmd
-                );
-            }
+    let mut console = ConsoleReader::spawn()?;
+
+    while let Some(line) = console.read_line().await {
+        dispatch_line(&line).await;
+
+        if exit_requested() {
+            break;
         }
-        stdout.write_all(b">>").await?;
-        stdout.flush().await?;
     }
 
     Ok(())
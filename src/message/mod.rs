@@ -1,3 +1,5 @@
+pub mod rich;
+
 pub trait Message {
     fn sender(&self) {}
 }
@@ -0,0 +1,174 @@
+use std::error::Error;
+use std::mem;
+use std::path::PathBuf;
+
+use ricq::msg::elem::{At, Text};
+use ricq::msg::MessageChain;
+use tokio::fs;
+
+use crate::contact::friend::Friend;
+use crate::contact::group::Group;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RichElement {
+    Text(String),
+    /// 目标为0时表示@全体成员
+    At(i64),
+    Image(PathBuf),
+}
+
+/// 解析富文本: `[at:QQ号]`, `[at:all]`, `[image:图片路径]`,
+/// 使用`\[`输入`[`, `\n`换行
+pub fn parse(s: &str) -> Vec<RichElement> {
+    let mut elements = vec![];
+    let mut text = String::new();
+    let mut rest = s;
+
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+
+        match c {
+            '\\' => match rest.chars().next() {
+                Some(next) => {
+                    rest = &rest[next.len_utf8()..];
+                    text.push(if next == 'n' { '\n' } else { next });
+                }
+                None => text.push('\\'),
+            },
+            '[' => {
+                let element = rest
+                    .find(']')
+                    .and_then(|end| parse_element(&rest[..end]).map(|e| (e, end)));
+
+                if let Some((element, end)) = element {
+                    if !text.is_empty() {
+                        elements.push(RichElement::Text(mem::take(&mut text)));
+                    }
+                    elements.push(element);
+                    rest = &rest[end + 1..];
+                } else {
+                    text.push('[');
+                }
+            }
+            c => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        elements.push(RichElement::Text(text));
+    }
+
+    elements
+}
+
+fn parse_element(s: &str) -> Option<RichElement> {
+    let (kind, arg) = s.split_once(':')?;
+
+    match kind {
+        "at" if arg == "all" => Some(RichElement::At(0)),
+        "at" => arg.parse().ok().map(RichElement::At),
+        "image" if !arg.is_empty() => Some(RichElement::Image(PathBuf::from(arg))),
+        _ => None,
+    }
+}
+
+pub async fn build_group_message(
+    group: &Group,
+    s: &str,
+) -> Result<MessageChain, Box<dyn Error + Send + Sync>> {
+    let mut chain = MessageChain::default();
+
+    for element in parse(s) {
+        match element {
+            RichElement::Text(s) => chain.push(Text::new(s)),
+            RichElement::At(0) => chain.push(At {
+                target: 0,
+                display: String::from("@全体成员"),
+            }),
+            RichElement::At(target) => {
+                let display = match group.find_member(target).await {
+                    Some(m) if !m.card_name().is_empty() => format!("@{}", m.card_name()),
+                    Some(m) => format!("@{}", m.nickname()),
+                    None => format!("@{}", target),
+                };
+
+                chain.push(At { target, display });
+            }
+            RichElement::Image(path) => {
+                let data = fs::read(&path).await?;
+                chain.push(group.upload_image(data).await?);
+            }
+        }
+    }
+
+    Ok(chain)
+}
+
+pub async fn build_friend_message(
+    friend: &Friend,
+    s: &str,
+) -> Result<MessageChain, Box<dyn Error + Send + Sync>> {
+    let mut chain = MessageChain::default();
+
+    for element in parse(s) {
+        match element {
+            RichElement::Text(s) => chain.push(Text::new(s)),
+            RichElement::At(target) => chain.push(Text::new(format!("@{}", target))),
+            RichElement::Image(path) => {
+                let data = fs::read(&path).await?;
+                chain.push(friend.upload_image(data).await?);
+            }
+        }
+    }
+
+    Ok(chain)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{parse, RichElement};
+
+    fn text(s: &str) -> RichElement {
+        RichElement::Text(String::from(s))
+    }
+
+    #[test]
+    fn plain_text() {
+        assert_eq!(parse("hello world"), [text("hello world")]);
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn elements() {
+        assert_eq!(
+            parse("hi [at:123][at:all] look [image:a/b.png]"),
+            [
+                text("hi "),
+                RichElement::At(123),
+                RichElement::At(0),
+                text(" look "),
+                RichElement::Image(PathBuf::from("a/b.png")),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_elements_stay_text() {
+        assert_eq!(parse("[at:abc]"), [text("[at:abc]")]);
+        assert_eq!(parse("[image:]"), [text("[image:]")]);
+        assert_eq!(parse("[face:1]"), [text("[face:1]")]);
+        assert_eq!(parse("a [at:1"), [text("a [at:1")]);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            parse(r"a\[b] \\ c\n[at:all]"),
+            [text("a[b] \\ c\n"), RichElement::At(0)]
+        );
+        assert_eq!(parse(r"\[at:1]"), [text("[at:1]")]);
+        assert_eq!(parse(r"end\"), [text(r"end\")]);
+    }
+}
@@ -0,0 +1,32 @@
+use atri_ffi::closure::FFIFn;
+use atri_ffi::future::FFIFuture;
+use atri_ffi::{Managed, RawString, RawVec};
+
+use crate::service::command::Command;
+
+pub extern "C" fn new_command(
+    name: RawString,
+    usage: RawString,
+    description: RawString,
+    f: FFIFn<FFIFuture<()>, RawVec<RawString>>,
+) -> Managed {
+    let name = name.to_string().unwrap_or_default();
+
+    let mut builder = Command::builder(name, move |args: Vec<String>| {
+        let args: Vec<RawString> = args.into_iter().map(RawString::from).collect();
+        let fu = f.invoke(RawVec::from(args));
+        async move {
+            fu.await;
+            Ok(())
+        }
+    });
+
+    if let Some(usage) = usage.to_string() {
+        builder = builder.usage(usage);
+    }
+    if let Some(description) = description.to_string() {
+        builder = builder.description(description);
+    }
+
+    Managed::from_value(builder.register())
+}
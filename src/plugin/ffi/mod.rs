@@ -1,8 +1,10 @@
 mod bot;
+mod command;
 mod listener;
 
 use std::sync::OnceLock;
 
+use crate::plugin::ffi::command::new_command;
 use crate::plugin::ffi::listener::new_listener;
 use crate::PluginManager;
 use atri_ffi::ffi::AtriVTable;
@@ -16,6 +18,7 @@ pub fn get_plugin_vtable() -> *const AtriVTable {
         plugin_manager_spawn,
         plugin_manager_block_on,
         new_listener,
+        new_command,
     })
 }
 
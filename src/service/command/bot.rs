@@ -0,0 +1,139 @@
+use tracing::error;
+
+use crate::bot::status::format_elapsed;
+use crate::service::command::CommandResult;
+use crate::service::login::{login_account, logout_account};
+use crate::{get_app, get_runtime};
+
+static BOT_HELP_INFO: &str = "\
+bot list: List all bots
+bot login <account>: Login the bot with the login config
+bot logout <account>: Logout the bot
+bot relogin <account>: Logout the bot and login again
+bot status <account>: Show the status of the bot
+";
+
+fn elapsed(time: Option<i64>) -> String {
+    match time {
+        Some(time) => format!("{} ago", format_elapsed(time)),
+        None => String::from("never"),
+    }
+}
+
+pub async fn status_command(_: Vec<String>) -> CommandResult {
+    let status = get_app().status();
+
+    println!(
+        "{}/{} bots online, {} messages received, {} sent",
+        status.online,
+        status.bots.len(),
+        status.messages_received,
+        status.messages_sent
+    );
+    for bot in status.bots {
+        println!(
+            "{}: {} (login {}, heartbeat {})",
+            bot.id,
+            bot.state,
+            elapsed(bot.login_time),
+            elapsed(bot.last_heartbeat)
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn bot_command(args: Vec<String>) -> CommandResult {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["list"] => {
+            let mut bots = get_app().bots();
+            bots.sort_by_key(|b| b.id());
+
+            if bots.is_empty() {
+                println!("No bot");
+            }
+
+            for bot in bots {
+                println!(
+                    "{}: {} [{}]",
+                    bot.id(),
+                    bot.nickname().await,
+                    bot.status().state
+                );
+            }
+        }
+        [sub @ ("login" | "logout" | "relogin" | "status"), account] => {
+            let account: i64 = match account.parse() {
+                Ok(account) => account,
+                Err(_) => {
+                    println!("Invalid account '{}'", account);
+                    return Ok(());
+                }
+            };
+
+            match *sub {
+                "login" => {
+                    if let Some(bot) = get_app().bot(account) {
+                        if bot.is_online() {
+                            println!("{} is already online", bot);
+                            return Ok(());
+                        }
+                    }
+
+                    get_runtime().spawn(async move {
+                        if let Err(e) = login_account(account).await {
+                            error!("Bot({})登陆失败: {:?}", account, e);
+                        }
+                    });
+                }
+                "logout" => {
+                    if logout_account(account).await.is_none() {
+                        println!("Bot({}) not found", account);
+                    }
+                }
+                "relogin" => {
+                    get_runtime().spawn(async move {
+                        logout_account(account).await;
+                        if let Err(e) = login_account(account).await {
+                            error!("Bot({})登陆失败: {:?}", account, e);
+                        }
+                    });
+                }
+                "status" => {
+                    let bot = if let Some(bot) = get_app().bot(account) {
+                        bot
+                    } else {
+                        println!("Bot({}) not found", account);
+                        return Ok(());
+                    };
+
+                    let status = bot.status();
+
+                    println!("{}", bot);
+                    println!("nickname: {}", bot.nickname().await);
+                    println!("state: {}", status.state);
+                    println!("login time: {}", elapsed(status.login_time));
+                    println!("last heartbeat: {}", elapsed(status.last_heartbeat));
+                    println!(
+                        "messages: {} received, {} sent",
+                        status.messages_received, status.messages_sent
+                    );
+                    println!("groups: {}", bot.groups().len());
+                    match status.server {
+                        Some(server) => println!("server: {}", server),
+                        None => println!("server: none"),
+                    }
+                    println!("work dir: {:?}", bot.work_dir());
+                }
+                _ => unreachable!(),
+            }
+        }
+        _ => {
+            print!("{}", BOT_HELP_INFO);
+        }
+    }
+
+    Ok(())
+}
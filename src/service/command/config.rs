@@ -0,0 +1,63 @@
+use tokio::fs;
+
+use crate::service::command::CommandResult;
+use crate::service::login::credential::{encrypt_config_source, load_or_create_key};
+use crate::service::login::login_config_path;
+use crate::service::login::validate::{check_login_config, Severity};
+
+static CONFIG_HELP_INFO: &str = "\
+config check: Check the login config and show the problems
+config encrypt: Encrypt the plain passwords in the login config
+config encrypt --md5: Replace the plain passwords with their md5
+";
+
+pub async fn config_command(args: Vec<String>) -> CommandResult {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["check"] => {
+            let diagnostics = check_login_config().await?;
+            let errors = diagnostics
+                .iter()
+                .filter(|d| d.severity == Severity::Error)
+                .count();
+
+            for diagnostic in &diagnostics {
+                println!("{}", diagnostic);
+            }
+
+            println!(
+                "{}: {} error(s), {} warning(s)",
+                login_config_path().display(),
+                errors,
+                diagnostics.len() - errors
+            );
+        }
+        ["encrypt"] => {
+            let key = load_or_create_key()?;
+            encrypt_passwords(Some(&key)).await?;
+        }
+        ["encrypt", "--md5"] => {
+            encrypt_passwords(None).await?;
+        }
+        _ => print!("{}", CONFIG_HELP_INFO),
+    }
+
+    Ok(())
+}
+
+async fn encrypt_passwords(key: Option<&[u8; 32]>) -> CommandResult {
+    let path = login_config_path();
+    let source = fs::read_to_string(&path).await?;
+    let (encrypted, count) = encrypt_config_source(&source, key)?;
+
+    if count == 0 {
+        println!("No plain password found");
+        return Ok(());
+    }
+
+    fs::write(&path, encrypted).await?;
+    println!("{} password(s) in {} replaced", count, path.display());
+
+    Ok(())
+}
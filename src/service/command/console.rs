@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+use std::sync::mpsc as std_mpsc;
+use std::{io, thread};
+
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use tokio::sync::mpsc;
+use tracing::error;
+
+use crate::config;
+use crate::service::command::get_command_registry;
+
+fn history_file_buf() -> PathBuf {
+    let mut p = config::service_config_dir_buf();
+    p.push("console_history");
+    p
+}
+
+pub struct ConsoleReader {
+    lines: mpsc::Receiver<String>,
+    ready: std_mpsc::Sender<()>,
+}
+
+impl ConsoleReader {
+    pub fn spawn() -> io::Result<Self> {
+        let (tx, rx) = mpsc::channel(1);
+        let (ready_tx, ready_rx) = std_mpsc::channel::<()>();
+
+        thread::Builder::new()
+            .name("Console".into())
+            .spawn(move || {
+                let mut editor = match Editor::<CommandHelper>::new() {
+                    Ok(editor) => editor,
+                    Err(e) => {
+                        error!("无法初始化控制台: {}", e);
+                        return;
+                    }
+                };
+                editor.set_helper(Some(CommandHelper));
+
+                let history = history_file_buf();
+                let _ = editor.load_history(&history);
+
+                // wait until the last line was handled
+                while ready_rx.recv().is_ok() {
+                    let line = match editor.readline(">>") {
+                        Ok(line) => {
+                            if !line.trim().is_empty() {
+                                editor.add_history_entry(line.as_str());
+                                let _ = editor.save_history(&history);
+                            }
+                            line
+                        }
+                        Err(ReadlineError::Interrupted | ReadlineError::Eof) => {
+                            String::from("exit")
+                        }
+                        Err(e) => {
+                            error!("读取控制台输入失败: {}", e);
+                            break;
+                        }
+                    };
+
+                    if tx.blocking_send(line).is_err() {
+                        break;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            lines: rx,
+            ready: ready_tx,
+        })
+    }
+
+    pub async fn read_line(&mut self) -> Option<String> {
+        self.ready.send(()).ok()?;
+        self.lines.recv().await
+    }
+}
+
+struct CommandHelper;
+
+impl Completer for CommandHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0);
+        let previous: Vec<&str> = line[..start].split_whitespace().collect();
+
+        let candidates = get_command_registry().complete(&previous, &line[start..]);
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for CommandHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CommandHelper {}
+
+impl Validator for CommandHelper {}
+
+impl Helper for CommandHelper {}
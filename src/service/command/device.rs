@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+use tokio::fs;
+
+use crate::bot::device::{convert_device, random_device, read_device, write_device};
+use crate::service::command::CommandResult;
+use crate::{config, get_app};
+
+static DEVICE_HELP_INFO: &str = "\
+device show <account>: Show the device info of the bot
+device regenerate <account> [--seed <seed>]: Generate a new device, the same seed generates the same device
+device import <account> <file>: Import the device from a ricq, mirai or go-cqhttp device file
+";
+
+fn work_dir_buf(account: i64) -> PathBuf {
+    let mut p = config::bots_dir_buf();
+    p.push(account.to_string());
+    p
+}
+
+fn parse_account(s: &str) -> Result<i64, String> {
+    s.parse().map_err(|_| format!("Invalid account '{}'", s))
+}
+
+/// 设备信息改变后旧的token不再可用
+async fn on_device_changed(account: i64) {
+    let token = work_dir_buf(account).join("token.json");
+    if token.is_file() && fs::remove_file(&token).await.is_ok() {
+        println!("The token of Bot({}) was removed", account);
+    }
+
+    if get_app().bot(account).map(|b| b.is_online()) == Some(true) {
+        println!(
+            "Bot({}) is online, use 'bot relogin {}' to apply the new device",
+            account, account
+        );
+    }
+}
+
+pub async fn device_command(args: Vec<String>) -> CommandResult {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["show", account] => {
+            let account = parse_account(account)?;
+            let device = read_device(&work_dir_buf(account)).await?;
+
+            println!("model: {} {}", device.brand, device.model);
+            println!("display: {}", device.display);
+            println!(
+                "os: {} {} (sdk {})",
+                device.os_type, device.version.release, device.version.sdk
+            );
+            println!("finger print: {}", device.finger_print);
+            println!("imei: {}", device.imei);
+            println!("android id: {}", device.android_id);
+            println!("mac address: {}", device.mac_address);
+        }
+        ["regenerate", account, rest @ ..] => {
+            let account = parse_account(account)?;
+            let seed = match rest {
+                [] => None,
+                ["--seed", seed] => Some(
+                    seed.parse::<u64>()
+                        .map_err(|_| format!("Invalid seed '{}'", seed))?,
+                ),
+                _ => {
+                    print!("{}", DEVICE_HELP_INFO);
+                    return Ok(());
+                }
+            };
+
+            let device = random_device(seed);
+            write_device(&work_dir_buf(account), &device).await?;
+            println!(
+                "New device generated for Bot({}): {} {}",
+                account, device.brand, device.model
+            );
+            on_device_changed(account).await;
+        }
+        ["import", account, file] => {
+            let account = parse_account(account)?;
+            let s = fs::read_to_string(file).await?;
+            let (device, format) = convert_device(&s)?;
+
+            write_device(&work_dir_buf(account), &device).await?;
+            println!(
+                "Device imported for Bot({}) from {} format: {} {}",
+                account, format, device.brand, device.model
+            );
+            on_device_changed(account).await;
+        }
+        _ => print!("{}", DEVICE_HELP_INFO),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use crate::service::command::CommandResult;
+use crate::service::listeners::get_global_worker;
+
+pub async fn listeners_command(_: Vec<String>) -> CommandResult {
+    let listeners = get_global_worker().listeners();
+
+    for info in &listeners {
+        println!(
+            "{:>6} {:<8}{:<32}failures: {}",
+            info.id,
+            info.priority.to_string(),
+            info.name,
+            info.failures
+        );
+    }
+
+    println!("{} listener(s) registered", listeners.len());
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use crate::contact::friend::Friend;
+use crate::contact::group::Group;
+use crate::message::rich::{build_friend_message, build_group_message};
+use crate::service::command::CommandResult;
+use crate::{get_app, Bot};
+
+static SEND_HELP_INFO: &str = "\
+send group <id> <message>: Send the message to the group
+send friend <id> <message>: Send the message to the friend
+Message syntax: [at:<uin>], [at:all], [image:<path>], use '\\[' for '[' and '\\n' for a new line
+";
+
+fn parse_id(s: &str) -> Result<i64, String> {
+    s.parse().map_err(|_| format!("Invalid id '{}'", s))
+}
+
+fn find_bot(s: &str) -> Result<Bot, String> {
+    let id = parse_id(s)?;
+    get_app()
+        .bot(id)
+        .ok_or_else(|| format!("Bot({}) not found", id))
+}
+
+async fn find_group(bot: &Bot, s: &str) -> Result<Group, String> {
+    let id = parse_id(s)?;
+    bot.find_group(id)
+        .await
+        .ok_or_else(|| format!("Group({}) not found in {}", id, bot))
+}
+
+async fn find_any_group(group_id: i64) -> Option<Group> {
+    let app = get_app();
+
+    if let Some(bot) = app.group_bot(group_id).and_then(|id| app.bot(id)) {
+        if bot.is_online() {
+            if let Some(group) = bot.find_group(group_id).await {
+                return Some(group);
+            }
+        }
+    }
+
+    for bot in app.bots() {
+        if bot.is_online() && bot.has_group(group_id) {
+            return bot.find_group(group_id).await;
+        }
+    }
+
+    None
+}
+
+/// 先在所有Bot已缓存的好友列表中查找, 都没有时才依次刷新好友列表
+async fn find_any_friend(friend_id: i64) -> Option<Friend> {
+    let bots: Vec<Bot> = get_app()
+        .bots()
+        .into_iter()
+        .filter(|bot| bot.is_online())
+        .collect();
+
+    if let Some(friend) = bots.iter().find_map(|bot| bot.cached_friend(friend_id)) {
+        return Some(friend);
+    }
+
+    for bot in bots {
+        if let Some(friend) = bot.find_friend(friend_id).await {
+            return Some(friend);
+        }
+    }
+
+    None
+}
+
+pub async fn send_command(args: Vec<String>) -> CommandResult {
+    let (target, id, message) = match args.as_slice() {
+        [target, id, message] => (target.as_str(), parse_id(id)?, message.as_str()),
+        _ => {
+            print!("{}", SEND_HELP_INFO);
+            return Ok(());
+        }
+    };
+
+    match target {
+        "group" => {
+            let group = find_any_group(id)
+                .await
+                .ok_or_else(|| format!("No online bot in Group({})", id))?;
+
+            let chain = build_group_message(&group, message).await?;
+            group.send_message(chain).await?;
+        }
+        "friend" => {
+            let friend = find_any_friend(id)
+                .await
+                .ok_or_else(|| format!("No online bot has Friend({})", id))?;
+
+            let chain = build_friend_message(&friend, message).await?;
+            friend.send_message(chain).await?;
+        }
+        _ => {
+            print!("{}", SEND_HELP_INFO);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn groups_command(args: Vec<String>) -> CommandResult {
+    let bot = match args.as_slice() {
+        [bot] => find_bot(bot)?,
+        _ => {
+            println!("Usage: groups <bot>");
+            return Ok(());
+        }
+    };
+
+    let mut groups = bot.groups();
+    groups.sort_by_key(|g| g.id());
+
+    for group in groups {
+        println!(
+            "{}: {} ({} members)",
+            group.id(),
+            group.name(),
+            group.member_count()
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn members_command(args: Vec<String>) -> CommandResult {
+    let (bot, group) = match args.as_slice() {
+        [bot, group] => (find_bot(bot)?, group),
+        _ => {
+            println!("Usage: members <bot> <group>");
+            return Ok(());
+        }
+    };
+
+    let group = find_group(&bot, group).await?;
+    let mut members = group.refresh_member_list().await?;
+    members.sort_by_key(|m| m.id());
+
+    for member in members {
+        if member.card_name().is_empty() {
+            println!("{}: {}", member.id(), member.nickname());
+        } else {
+            println!(
+                "{}: {} ({})",
+                member.id(),
+                member.card_name(),
+                member.nickname()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn member_command(args: Vec<String>) -> CommandResult {
+    let (bot, group, uin) = match args.as_slice() {
+        [bot, group, uin] => (find_bot(bot)?, group, parse_id(uin)?),
+        _ => {
+            println!("Usage: member <bot> <group> <uin>");
+            return Ok(());
+        }
+    };
+
+    let group = find_group(&bot, group).await?;
+    let member = group
+        .find_member(uin)
+        .await
+        .ok_or_else(|| format!("Member({}) not found in {}", uin, group))?;
+
+    println!("{} in {}({})", member.id(), group.name(), group.id());
+    println!("nickname: {}", member.nickname());
+    println!("card name: {}", member.card_name());
+    println!("special title: {}", member.special_title());
+    println!("level: {}", member.level());
+    println!("permission: {:?}", member.permission());
+    println!("join time: {}", member.join_time());
+
+    Ok(())
+}
@@ -0,0 +1,576 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use tokio::sync::oneshot;
+use tracing::warn;
+
+pub mod bot;
+pub mod config;
+pub mod console;
+pub mod device;
+pub mod listener;
+pub mod message;
+pub mod runtime;
+
+pub type CommandResult = Result<(), Box<dyn Error + Send + Sync>>;
+
+type CommandHandler = Box<
+    dyn Fn(Vec<String>) -> Pin<Box<dyn Future<Output = CommandResult> + Send + 'static>>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+pub struct Command {
+    name: String,
+    usage: String,
+    description: String,
+    aliases: Vec<String>,
+    sub_commands: Vec<String>,
+    raw_after: Option<usize>,
+    handler: CommandHandler,
+}
+
+impl Command {
+    pub fn builder<F, Fu>(name: impl ToString, handler: F) -> CommandBuilder
+    where
+        F: Fn(Vec<String>) -> Fu,
+        F: Send + Sync + 'static,
+        Fu: Future<Output = CommandResult>,
+        Fu: Send + 'static,
+    {
+        let handler = Box::new(move |args: Vec<String>| {
+            let fu = handler(args);
+            let b: Box<dyn Future<Output = CommandResult> + Send + 'static> = Box::new(fu);
+
+            Box::into_pin(b)
+        });
+
+        CommandBuilder {
+            name: name.to_string(),
+            usage: None,
+            description: String::new(),
+            aliases: vec![],
+            sub_commands: vec![],
+            raw_after: None,
+            handler,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn usage(&self) -> &str {
+        &self.usage
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+pub struct CommandBuilder {
+    name: String,
+    usage: Option<String>,
+    description: String,
+    aliases: Vec<String>,
+    sub_commands: Vec<String>,
+    raw_after: Option<usize>,
+    handler: CommandHandler,
+}
+
+impl CommandBuilder {
+    pub fn usage(mut self, usage: impl ToString) -> Self {
+        self.usage = Some(usage.to_string());
+        self
+    }
+
+    pub fn description(mut self, description: impl ToString) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn alias(mut self, alias: impl ToString) -> Self {
+        self.aliases.push(alias.to_string());
+        self
+    }
+
+    /// 用于Tab补全的子指令
+    pub fn sub_commands<I, S>(mut self, sub_commands: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: ToString,
+    {
+        self.sub_commands
+            .extend(sub_commands.into_iter().map(|s| s.to_string()));
+        self
+    }
+
+    /// 只解析前`n`个参数, 之后的内容不做引号与转义处理, 原样作为最后一个参数传入
+    pub fn raw_after(mut self, n: usize) -> Self {
+        self.raw_after = Some(n);
+        self
+    }
+
+    fn build(self) -> Command {
+        let Self {
+            name,
+            usage,
+            description,
+            aliases,
+            sub_commands,
+            raw_after,
+            handler,
+        } = self;
+
+        Command {
+            usage: usage.unwrap_or_else(|| name.clone()),
+            name,
+            description,
+            aliases,
+            sub_commands,
+            raw_after,
+            handler,
+        }
+    }
+
+    pub fn register(self) -> CommandGuard {
+        let command = Arc::new(self.build());
+        get_command_registry().insert(command.clone());
+
+        CommandGuard { command }
+    }
+}
+
+pub struct CommandGuard {
+    command: Arc<Command>,
+}
+
+impl CommandGuard {
+    pub fn name(&self) -> &str {
+        self.command.name()
+    }
+}
+
+impl Drop for CommandGuard {
+    fn drop(&mut self) {
+        get_command_registry().remove(&self.command);
+    }
+}
+
+pub struct CommandRegistry {
+    commands: RwLock<BTreeMap<String, Arc<Command>>>,
+}
+
+impl CommandRegistry {
+    fn new() -> Self {
+        Self {
+            commands: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    fn insert(&self, command: Arc<Command>) {
+        let mut commands = self.commands.write().expect("Cannot write commands");
+
+        for key in std::iter::once(&command.name).chain(&command.aliases) {
+            if commands.insert(key.clone(), command.clone()).is_some() {
+                warn!("指令({})已存在, 将被覆盖", key);
+            }
+        }
+    }
+
+    fn remove(&self, command: &Arc<Command>) {
+        let mut commands = self.commands.write().expect("Cannot write commands");
+        commands.retain(|_, c| !Arc::ptr_eq(c, command));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Command>> {
+        let commands = self.commands.read().expect("Cannot read commands");
+        commands.get(name).cloned()
+    }
+
+    pub fn commands(&self) -> Vec<Arc<Command>> {
+        let commands = self.commands.read().expect("Cannot read commands");
+        commands
+            .iter()
+            .filter(|(key, c)| **key == c.name)
+            .map(|(_, c)| c.clone())
+            .collect()
+    }
+
+    pub fn help_info(&self) -> String {
+        let mut s = String::new();
+        for command in self.commands() {
+            s.push_str(&format!("{}: {}\n", command.usage, command.description));
+        }
+        s
+    }
+
+    pub fn complete(&self, previous: &[&str], word: &str) -> Vec<String> {
+        match previous {
+            [] => {
+                let commands = self.commands.read().expect("Cannot read commands");
+                commands
+                    .keys()
+                    .filter(|name| name.starts_with(word))
+                    .cloned()
+                    .collect()
+            }
+            [name] => self
+                .get(name)
+                .map(|c| {
+                    c.sub_commands
+                        .iter()
+                        .filter(|s| s.starts_with(word))
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => vec![],
+        }
+    }
+
+    pub async fn execute(&self, line: &str) {
+        let (name, rest) = match split_args(line, 1) {
+            Ok((mut args, rest)) if !args.is_empty() => (args.remove(0), rest),
+            Ok(_) => return,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+
+        let command = if let Some(c) = self.get(&name) {
+            c
+        } else {
+            println!(
+                "Unknown command '{}', use 'help' to show the help info",
+                name
+            );
+            return;
+        };
+
+        let args = match command.raw_after {
+            Some(n) => split_args(rest, n).map(|(mut args, raw)| {
+                if !raw.is_empty() {
+                    args.push(raw.to_string());
+                }
+                args
+            }),
+            None => tokenize(rest),
+        };
+
+        let args = match args {
+            Ok(args) => args,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = (command.handler)(args).await {
+            println!("Error: {}", e);
+        }
+    }
+}
+
+pub fn get_command_registry() -> &'static CommandRegistry {
+    static REGISTRY: OnceLock<CommandRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let registry = CommandRegistry::new();
+
+        let help = Command::builder("help", |args: Vec<String>| async move {
+            let registry = get_command_registry();
+            match args.first().and_then(|name| registry.get(name)) {
+                Some(c) => println!("{}: {}", c.usage, c.description),
+                None => print!("{}", registry.help_info()),
+            }
+            Ok(())
+        })
+        .usage("help [command]")
+        .description("Show this info")
+        .alias("?")
+        .build();
+        registry.insert(Arc::new(help));
+
+        let exit = Command::builder("exit", |_| async {
+            println!("Stopping...");
+            request_exit();
+            Ok(())
+        })
+        .description("Exit this program")
+        .alias("quit")
+        .alias("stop")
+        .build();
+        registry.insert(Arc::new(exit));
+
+        registry
+    })
+}
+
+pub fn register_builtin_commands() {
+    let guard = Command::builder("bot", bot::bot_command)
+        .usage("bot <list|login|logout|relogin|status> [account]")
+        .description("Manage the bots, use 'bot help' to show the details")
+        .sub_commands(["list", "login", "logout", "relogin", "status", "help"])
+        .register();
+    mem::forget(guard);
+
+    let guard = Command::builder("status", bot::status_command)
+        .description("Show the status of all bots")
+        .register();
+    mem::forget(guard);
+
+    let guard = Command::builder("listeners", listener::listeners_command)
+        .description("List the registered listeners with their priority")
+        .register();
+    mem::forget(guard);
+
+    let guard = Command::builder("runtime", runtime::runtime_command)
+        .description("Show the threads and tasks of each runtime")
+        .register();
+    mem::forget(guard);
+
+    let guard = Command::builder("config", config::config_command)
+        .usage("config <check|encrypt>")
+        .description("Manage the login config, use 'config help' to show the details")
+        .sub_commands(["check", "encrypt", "help"])
+        .register();
+    mem::forget(guard);
+
+    let guard = Command::builder("device", device::device_command)
+        .usage("device <show|regenerate|import> <account>")
+        .description("Manage the device info of the bots, use 'device help' to show the details")
+        .sub_commands(["show", "regenerate", "import", "help"])
+        .register();
+    mem::forget(guard);
+
+    let guard = Command::builder("send", message::send_command)
+        .usage("send <group|friend> <id> <message>")
+        .description("Send a message as the bot, use 'send help' to show the message syntax")
+        .sub_commands(["group", "friend", "help"])
+        .raw_after(2)
+        .register();
+    mem::forget(guard);
+
+    let guard = Command::builder("groups", message::groups_command)
+        .usage("groups <bot>")
+        .description("List the groups of the bot")
+        .register();
+    mem::forget(guard);
+
+    let guard = Command::builder("members", message::members_command)
+        .usage("members <bot> <group>")
+        .description("List the members of the group")
+        .register();
+    mem::forget(guard);
+
+    let guard = Command::builder("member", message::member_command)
+        .usage("member <bot> <group> <uin>")
+        .description("Show the info of the group member")
+        .register();
+    mem::forget(guard);
+}
+
+static EXIT: AtomicBool = AtomicBool::new(false);
+
+pub fn request_exit() {
+    EXIT.store(true, Ordering::Release);
+}
+
+pub fn exit_requested() -> bool {
+    EXIT.load(Ordering::Acquire)
+}
+
+fn input_requests() -> &'static Mutex<VecDeque<oneshot::Sender<String>>> {
+    static REQUESTS: OnceLock<Mutex<VecDeque<oneshot::Sender<String>>>> = OnceLock::new();
+    REQUESTS.get_or_init(Default::default)
+}
+
+/// 等待控制台的下一行输入, 该行不会被当作指令执行
+pub async fn request_input() -> Option<String> {
+    let (tx, rx) = oneshot::channel();
+    input_requests()
+        .lock()
+        .expect("Cannot lock input requests")
+        .push_back(tx);
+
+    rx.await.ok()
+}
+
+/// 处理控制台输入的一行, 优先交给等待输入的请求
+pub async fn dispatch_line(line: &str) {
+    let mut line = line.to_owned();
+    loop {
+        let tx = input_requests()
+            .lock()
+            .expect("Cannot lock input requests")
+            .pop_front();
+
+        match tx {
+            Some(tx) => match tx.send(line) {
+                Ok(()) => return,
+                // the request was cancelled
+                Err(l) => line = l,
+            },
+            None => break,
+        }
+    }
+
+    get_command_registry().execute(&line).await;
+}
+
+#[derive(Debug)]
+pub enum TokenizeError {
+    UnclosedQuote(char),
+    TrailingEscape,
+}
+
+impl Display for TokenizeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnclosedQuote(c) => write!(f, "Unclosed quote {}", c),
+            Self::TrailingEscape => write!(f, "Nothing to escape after '\\'"),
+        }
+    }
+}
+
+impl Error for TokenizeError {}
+
+/// 按空白分割参数, 支持单双引号及`\`转义
+pub fn tokenize(line: &str) -> Result<Vec<String>, TokenizeError> {
+    split_args(line, usize::MAX).map(|(args, _)| args)
+}
+
+/// 只解析前`n`个参数, 剩余部分去除开头空白后原样返回
+pub fn split_args(line: &str, n: usize) -> Result<(Vec<String>, &str), TokenizeError> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None::<char>;
+    let mut chars = line.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        if args.len() == n {
+            return Ok((args, line[i..].trim_start()));
+        }
+
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('\''), c) => current.push(c),
+            (_, '\\') => {
+                let (_, next) = chars.next().ok_or(TokenizeError::TrailingEscape)?;
+                current.push(next);
+                in_arg = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(TokenizeError::UnclosedQuote(q));
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+
+    Ok((args, ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_args, tokenize, TokenizeError};
+
+    fn args(line: &str) -> Vec<String> {
+        tokenize(line).expect("tokenize failed")
+    }
+
+    #[test]
+    fn splits_on_whitespace() {
+        assert_eq!(args("send  group\t123 hi"), ["send", "group", "123", "hi"]);
+        assert_eq!(args("  help  "), ["help"]);
+    }
+
+    #[test]
+    fn empty_input() {
+        assert!(args("").is_empty());
+        assert!(args("   \t ").is_empty());
+    }
+
+    #[test]
+    fn quotes() {
+        assert_eq!(args(r#"say "hello world" x"#), ["say", "hello world", "x"]);
+        assert_eq!(args("say 'hello world'"), ["say", "hello world"]);
+        assert_eq!(args(r#"say "it's" 'a "b"'"#), ["say", "it's", r#"a "b""#]);
+        assert_eq!(args(r#"a"b c"d"#), ["ab cd"]);
+        assert_eq!(args(r#"say "" ''"#), ["say", "", ""]);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(args(r"say hello\ world"), ["say", "hello world"]);
+        assert_eq!(args(r#"say \"x\""#), ["say", r#""x""#]);
+        assert_eq!(args(r#"say "a\"b""#), ["say", r#"a"b"#]);
+        assert_eq!(args(r"say \\"), ["say", r"\"]);
+        // 单引号内不处理转义
+        assert_eq!(args(r"say 'a\b'"), ["say", r"a\b"]);
+    }
+
+    #[test]
+    fn unterminated() {
+        assert!(matches!(
+            tokenize(r#"say "hello"#),
+            Err(TokenizeError::UnclosedQuote('"'))
+        ));
+        assert!(matches!(
+            tokenize("say 'hello"),
+            Err(TokenizeError::UnclosedQuote('\''))
+        ));
+        assert!(matches!(
+            tokenize(r"say hello\"),
+            Err(TokenizeError::TrailingEscape)
+        ));
+    }
+
+    #[test]
+    fn split_keeps_raw_remainder() {
+        let (args, rest) = split_args(r#"group 123  "a  b" \[x\]"#, 2).unwrap();
+        assert_eq!(args, ["group", "123"]);
+        assert_eq!(rest, r#""a  b" \[x\]"#);
+
+        let (args, rest) = split_args("group 123", 2).unwrap();
+        assert_eq!(args, ["group", "123"]);
+        assert_eq!(rest, "");
+
+        // 剩余部分中未闭合的引号不影响解析
+        assert!(split_args(r#"group 123 it"s"#, 2).is_ok());
+    }
+
+    #[test]
+    fn raw_remainder_keeps_escapes_for_rich_text() {
+        let (_, rest) = split_args(r"group 123 a\[b] \\ c\n[at:all]", 2).unwrap();
+        assert_eq!(rest, r"a\[b] \\ c\n[at:all]");
+    }
+}
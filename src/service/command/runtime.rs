@@ -0,0 +1,25 @@
+use crate::service::command::CommandResult;
+use crate::service::listeners::get_global_worker;
+use crate::service::runtime::runtime_metrics;
+
+pub async fn runtime_command(_: Vec<String>) -> CommandResult {
+    println!(
+        "{:<10}{:>9}{:>9}{:>10}{:>9}",
+        "runtime", "workers", "threads", "spawned", "active"
+    );
+
+    for m in runtime_metrics() {
+        println!(
+            "{:<10}{:>9}{:>9}{:>10}{:>9}",
+            m.kind.to_string(),
+            m.worker_threads,
+            m.alive_threads,
+            m.spawned_tasks,
+            m.active_tasks
+        );
+    }
+
+    println!("Events in flight: {}", get_global_worker().in_flight());
+
+    Ok(())
+}
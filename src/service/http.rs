@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::PathBuf;
+
+use tracing::{error, info};
+
+use crate::config;
+use crate::config::http::HttpConfig;
+
+fn http_config_path() -> PathBuf {
+    let mut p = config::service_config_dir_buf();
+    p.push("http.toml");
+    p
+}
+
+fn read_http_config() -> HttpConfig {
+    let path = http_config_path();
+
+    if !path.is_file() {
+        let _ = fs::create_dir_all(config::service_config_dir_buf());
+        if let Err(e) = fs::write(&path, config::http::DEFAULT_CONFIG) {
+            error!("写入默认HTTP配置文件失败: {}", e);
+        }
+        return HttpConfig::default();
+    }
+
+    match fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| toml::from_str::<HttpConfig>(&s).map_err(|e| e.to_string()))
+    {
+        Ok(conf) => conf,
+        Err(e) => {
+            error!("读取HTTP配置文件失败: {}, 将不使用代理", e);
+            HttpConfig::default()
+        }
+    }
+}
+
+/// 根据配置创建全局HTTP客户端
+pub fn build_http_client() -> reqwest::Client {
+    client_with_proxy(read_http_config().proxy)
+}
+
+fn client_with_proxy(proxy: Option<String>) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder();
+
+    if let Some(proxy) = proxy.filter(|p| !p.is_empty()) {
+        match reqwest::Proxy::all(&proxy) {
+            Ok(p) => {
+                info!("HTTP客户端使用代理: {}", proxy);
+                builder = builder.proxy(p);
+            }
+            Err(e) => error!("HTTP代理({})配置错误: {}, 将不使用代理", proxy, e),
+        }
+    }
+
+    builder.build().unwrap_or_else(|e| {
+        error!("创建HTTP客户端失败: {}, 将使用默认配置", e);
+        reqwest::Client::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    use super::client_with_proxy;
+
+    #[tokio::test]
+    async fn requests_go_through_proxy() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // 代理替身, 记录收到的请求头并直接返回响应
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut head = vec![];
+            loop {
+                let mut line = String::new();
+                stream.read_line(&mut line).await.unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                head.push(line.trim_end().to_owned());
+            }
+
+            stream
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\npong")
+                .await
+                .unwrap();
+            head
+        });
+
+        let client = client_with_proxy(Some(format!("http://127.0.0.1:{}", port)));
+        let body = client
+            .get("http://atri.invalid/ping")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "pong");
+
+        let head = server.await.unwrap();
+        assert_eq!(head[0], "GET http://atri.invalid/ping HTTP/1.1");
+        assert!(
+            head.iter()
+                .any(|l| l.eq_ignore_ascii_case("host: atri.invalid")),
+            "{:?}",
+            head
+        );
+    }
+}
@@ -1,84 +1,297 @@
-use std::collections::LinkedList;
-use std::sync::atomic::Ordering;
-use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
 
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::Notify;
+use tokio::task::JoinError;
+use tracing::{error, warn};
 
-use crate::{Event, Listener};
+use crate::event::delivery::{Sequencer, Ticket};
+use crate::event::listener::{current_listener_id, run_as, ListenerResult, Priority};
+use crate::event::middleware::{Middleware, Middlewares, Next, Outcome};
+use crate::event::session::SessionRouter;
+use crate::{get_listener_runtime, Event, Listener};
 
-type LimitedListeners = LinkedList<Arc<RwLock<Option<Arc<Listener>>>>>;
+type Snapshot = Arc<Vec<Arc<Listener>>>;
+
+/// 每个优先级的监听器及其处理顺序的凭证
+type Plan = Vec<Vec<(Arc<Listener>, Option<Ticket>)>>;
+
+/// 所有中间件运行后, 选出监听器的方式
+pub(crate) enum Dispatch {
+    /// 按接收顺序选出监听器并取号
+    Arrival(Ticket),
+    /// 同步处理, 不为监听器取号; 指定的监听器不会处理此事件
+    Nested(Option<usize>),
+}
+
+/// 同一优先级的监听器, 修改时复制整个列表, 处理事件时只需克隆当前快照
+#[derive(Default)]
+struct PriorityListeners {
+    snapshot: RwLock<Snapshot>,
+}
+
+impl PriorityListeners {
+    fn snapshot(&self) -> Snapshot {
+        self.snapshot.read().expect("Cannot read listeners").clone()
+    }
+
+    fn update<F: FnOnce(&mut Vec<Arc<Listener>>)>(&self, f: F) {
+        let mut lock = self.snapshot.write().expect("Cannot write listeners");
+        let mut listeners = Vec::clone(&lock);
+        f(&mut listeners);
+        *lock = Arc::new(listeners);
+    }
+}
+
+/// 监听器的信息
+#[derive(Debug, Clone)]
+pub struct ListenerInfo {
+    pub id: usize,
+    pub name: Arc<String>,
+    pub priority: Priority,
+    pub failures: u64,
+}
 
 pub struct ListenerWorker {
-    listeners: Vec<LimitedListeners>,
-    listener_rx: Mutex<tokio::sync::mpsc::Receiver<Arc<Listener>>>,
-    listener_tx: tokio::sync::mpsc::Sender<Arc<Listener>>,
+    listeners: [PriorityListeners; Priority::COUNT],
+    middlewares: RwLock<Middlewares>,
+    /// 按接收顺序为事件取号, 保证按接收顺序选出监听器并取号
+    arrivals: Arc<Sequencer>,
+    in_flight: AtomicUsize,
+    idle: Notify,
+    /// 毫秒, 0表示不限制
+    default_timeout: AtomicU64,
 }
 
 impl ListenerWorker {
     pub fn new() -> Self {
-        let mut listeners = vec![];
+        ListenerWorker {
+            listeners: Default::default(),
+            middlewares: Default::default(),
+            arrivals: Default::default(),
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+            default_timeout: AtomicU64::new(0),
+        }
+    }
 
-        for _ in 0..5 {
-            listeners.push(LinkedList::new())
+    /// 未设置超时的监听器使用的超时时间, `None`表示不限制, 默认不限制
+    pub fn default_timeout(&self) -> Option<Duration> {
+        match self.default_timeout.load(Ordering::Relaxed) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
         }
+    }
 
-        let (tx, rx) = tokio::sync::mpsc::channel(61);
+    pub fn set_default_timeout(&self, timeout: Option<Duration>) {
+        let millis = timeout.map(|t| t.as_millis().max(1) as u64).unwrap_or(0);
+        self.default_timeout.store(millis, Ordering::Relaxed);
+    }
 
-        ListenerWorker {
-            listeners,
-            listener_rx: rx.into(),
-            listener_tx: tx,
+    /// 为即将分发的事件取号, 需要在接收事件时同步调用;
+    /// 事件按取号的顺序交给顺序处理的监听器, 丢弃时放行之后的事件
+    pub(crate) fn reserve(&self) -> Ticket {
+        self.arrivals.take()
+    }
+
+    /// 在监听器运行时中处理事件, 并记录正在处理的事件数量
+    pub fn dispatch(&'static self, event: Event) {
+        self.dispatch_reserved(self.reserve(), event);
+    }
+
+    /// 按[`ListenerWorker::reserve`]取得的顺序分发事件
+    pub(crate) fn dispatch_reserved(&'static self, arrival: Ticket, event: Event) {
+        struct InFlight(&'static ListenerWorker);
+
+        impl Drop for InFlight {
+            fn drop(&mut self) {
+                if self.0.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+                    self.0.idle.notify_waiters();
+                }
+            }
         }
+
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        let guard = InFlight(self);
+        let middlewares = self.middlewares();
+        get_listener_runtime().spawn(async move {
+            let _guard = guard;
+            Next::new(self, &middlewares, Dispatch::Arrival(arrival))
+                .run(event)
+                .await;
+        });
     }
 
-    pub async fn schedule(&self, listener: Listener) {
-        let arc = Arc::new(listener);
-        let _ = self.listener_tx.send(arc).await;
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
     }
 
-    pub async fn handle(&self, event: &Event) {
-        let mut handlers = vec![];
-        for list in &self.listeners {
-            handlers.reserve(list.len());
-            for opt in list {
-                let opt = opt.clone();
-                let event = event.clone();
-                let handle = tokio::spawn(async move {
-                    let listener = {
-                        let lock = opt.read().await;
-                        lock.clone()
-                    };
-
-                    if let Some(listener) = listener {
-                        let close_listener = async {
-                            let mut lock = opt.write().await;
-                            *lock = None;
-                        };
-
-                        if let Some(ref mutex) = listener.concurrent_mutex {
-                            mutex.lock().await;
-                        }
+    /// 等待所有正在处理的事件完成, 超时返回`false`
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, async {
+            loop {
+                let notified = self.idle.notified();
+                if self.in_flight() == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
 
-                        if listener.closed.load(Ordering::Acquire) {
-                            close_listener.await;
-                            return;
-                        }
+    /// 注册监听器, 注册后立即生效
+    pub fn register(&self, listener: Listener) {
+        let listener = Arc::new(listener);
+        self.listeners[listener.priority as usize].update(|list| list.push(listener));
+    }
+
+    /// 移除监听器, 正在处理中的事件不受影响
+    pub fn unregister(&self, id: usize, priority: Priority) -> bool {
+        let mut removed = false;
+        self.listeners[priority as usize].update(|list| {
+            let len = list.len();
+            list.retain(|l| l.id != id);
+            removed = list.len() != len;
+        });
+
+        removed
+    }
+
+    /// 当前已注册的监听器, 按优先级排序
+    pub fn listeners(&self) -> Vec<ListenerInfo> {
+        self.listeners
+            .iter()
+            .flat_map(|list| {
+                list.snapshot()
+                    .iter()
+                    .map(Listener::info)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// 选出接收此事件的监听器, `ordered`为真时为需要顺序处理的监听器取号;
+    /// `exclude`指定的监听器不会处理此事件
+    fn plan(&self, event: &Event, exclude: Option<usize>, ordered: bool) -> Plan {
+        self.listeners
+            .iter()
+            .map(|list| {
+                list.snapshot()
+                    .iter()
+                    .filter(|listener| Some(listener.id) != exclude && listener.wants(event))
+                    .map(|listener| {
+                        let ticket = ordered.then(|| listener.delivery.reserve(event));
+                        (listener.clone(), ticket.flatten())
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// 注册中间件, `order`越小越先运行
+    pub fn add_middleware<M: Middleware>(&self, order: i32, middleware: M) {
+        let mut lock = self.middlewares.write().expect("Cannot write middlewares");
+        let mut middlewares = Vec::clone(&lock);
+        let pos = middlewares.partition_point(|(o, _)| *o <= order);
+        let middleware: Arc<dyn Middleware> = Arc::new(middleware);
+        middlewares.insert(pos, (order, middleware));
+        *lock = Arc::new(middlewares);
+    }
+
+    fn middlewares(&self) -> Middlewares {
+        self.middlewares
+            .read()
+            .expect("Cannot read middlewares")
+            .clone()
+    }
+
+    /// 处理事件并等待所有监听器完成;
+    /// 调用者可能是正在处理事件的顺序处理的监听器, 互相等待将造成死锁, 因此不为监听器取号, 不保证顺序;
+    /// 在监听器中调用时, 事件不会交给该监听器本身
+    pub async fn handle(&self, event: Event) -> Outcome {
+        let middlewares = self.middlewares();
+        Next::new(self, &middlewares, Dispatch::Nested(current_listener_id()))
+            .run(event)
+            .await
+    }
 
-                        let fu = (listener.handler)(event);
-                        let con: bool = fu.await;
+    /// 在所有中间件之后选出监听器并处理事件, 中间件修改后的事件同样参与筛选
+    pub(crate) async fn deliver(&self, event: &Event, dispatch: Dispatch) {
+        let plan = match dispatch {
+            Dispatch::Arrival(arrival) => {
+                // 之前的事件取号后才能取号, 保证顺序处理的监听器按接收顺序处理
+                arrival.wait_turn().await;
+                self.plan(event, None, true)
+            }
+            Dispatch::Nested(exclude) => self.plan(event, exclude, false),
+        };
+
+        self.run(event, plan).await;
+    }
 
-                        if !con {
-                            close_listener.await;
-                        };
+    async fn run(&self, event: &Event, plan: Plan) {
+        for tier in plan {
+            let mut handlers = Vec::with_capacity(tier.len());
+
+            for (listener, ticket) in tier {
+                let arc = listener.clone();
+                let event = event.clone();
+                let timeout = listener.timeout.or_else(|| self.default_timeout());
+                let handle = get_listener_runtime().spawn(async move {
+                    // 处理完成后才放行下一个事件
+                    if let Some(ref ticket) = ticket {
+                        ticket.wait_turn().await;
+                    }
+                    let _ticket = ticket;
+
+                    // 已被同级的监听器处理
+                    if arc.closed.load(Ordering::Acquire) || event.is_consumed() {
+                        return Handled::Skipped;
+                    }
+
+                    // 超时从轮到此事件时开始计算, 排队等待之前的事件不计入
+                    let task = run_as(arc.id, arc.name.clone(), (arc.handler)(event));
+                    match timeout {
+                        Some(timeout) => tokio::time::timeout(timeout, task)
+                            .await
+                            .map_or(Handled::TimedOut, Handled::Finished),
+                        None => Handled::Finished(task.await),
                     }
                 });
 
-                handlers.push(handle);
+                handlers.push((listener, timeout, handle));
             }
 
-            for _ in 0..handlers.len() {
-                let handle = handlers.pop().expect("Cannot get handle");
-                let _ = handle.await;
+            for (listener, timeout, handle) in handlers {
+                match handle.await {
+                    Ok(Handled::Skipped) => {}
+                    Ok(Handled::Finished(Ok(true))) => listener.failures.record_success(),
+                    Ok(Handled::Finished(Ok(false))) => self.close(&listener),
+                    Ok(Handled::TimedOut) => {
+                        warn!(
+                            "监听器({})处理事件超时({}ms), 已取消",
+                            listener.name,
+                            timeout.unwrap_or_default().as_millis()
+                        );
+                        self.record_failure(&listener);
+                    }
+                    Ok(Handled::Finished(Err(e))) => {
+                        error!("监听器({})处理事件时发生错误: {}", listener.name, e);
+                        self.record_failure(&listener);
+                    }
+                    Err(e) if e.is_panic() => {
+                        error!(
+                            "监听器({})处理事件时发生panic: {}",
+                            listener.name,
+                            panic_message(e)
+                        );
+                        self.record_failure(&listener);
+                    }
+                    Err(_) => {}
+                }
             }
 
             if event.is_intercepted() {
@@ -87,30 +300,161 @@ impl ListenerWorker {
         }
     }
 
-    pub async fn start(&self) {
-        let mut lock = self.listener_rx.lock().await;
-
-        'add: while let Some(l) = lock.recv().await {
-            let list = &self.listeners[l.priority as usize];
+    fn close(&self, listener: &Listener) {
+        listener.closed.store(true, Ordering::Release);
+        self.unregister(listener.id, listener.priority);
+    }
 
-            for opt in list {
-                let node = { opt.read().await.clone() };
+    fn record_failure(&self, listener: &Listener) {
+        let consecutive = listener.failures.record_failure();
 
-                if node.is_none() {
-                    let mut wl = opt.write().await;
-                    *wl = Some(l);
-                    continue 'add;
-                }
+        if let Some(max) = listener.max_failures {
+            if consecutive >= max && !listener.closed.load(Ordering::Acquire) {
+                warn!(
+                    "监听器({})连续失败{}次, 已被关闭",
+                    listener.name, consecutive
+                );
+                self.close(listener);
             }
-
-            #[allow(clippy::cast_ref_to_mut)]
-            let list = unsafe { &mut *(list as *const _ as *mut LimitedListeners) };
-            list.push_back(Arc::new(RwLock::new(Some(l))));
         }
     }
 }
 
+/// 单个监听器处理一次事件的结果
+enum Handled {
+    /// 监听器已关闭或事件已被消费, 处理器没有运行, 不影响失败计数
+    Skipped,
+    TimedOut,
+    Finished(ListenerResult),
+}
+
+fn panic_message(e: JoinError) -> String {
+    let panic = e.into_panic();
+
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        String::from("Unknown panic")
+    }
+}
+
 pub fn get_global_worker() -> &'static ListenerWorker {
     static WORKER: OnceLock<ListenerWorker> = OnceLock::new();
-    WORKER.get_or_init(|| ListenerWorker::new())
+    WORKER.get_or_init(|| {
+        let worker = ListenerWorker::new();
+        // 会话在所有中间件之后接收消息, 黑名单等中间件对会话同样生效
+        worker.add_middleware(i32::MAX, SessionRouter);
+        worker
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use regex::Regex;
+
+    use super::ListenerWorker;
+    use crate::event::middleware::{Middleware, Next, Outcome};
+    use crate::event::testing::{bot, friend_message, text};
+    use crate::event::{Event, FriendMessageEvent};
+    use crate::{Bot, Listener};
+
+    /// 将好友消息改写为"ping"
+    struct Rewrite(Bot);
+
+    #[async_trait]
+    impl Middleware for Rewrite {
+        async fn handle(&self, event: Event, next: Next<'_>) -> Outcome {
+            let event = match event {
+                Event::FriendMessageEvent(e) => {
+                    friend_message(&self.0, e.message().from_uin, text("ping"))
+                }
+                event => event,
+            };
+            next.run(event).await
+        }
+    }
+
+    fn worker(bot: &Bot) -> &'static ListenerWorker {
+        let worker: &'static ListenerWorker = Box::leak(Box::new(ListenerWorker::new()));
+        worker.add_middleware(0, Rewrite(bot.clone()));
+        worker.register(
+            Listener::listening_on(|e: FriendMessageEvent| async move {
+                e.consume();
+                true
+            })
+            .with_name("ping")
+            .text_matches(Regex::new("^ping$").unwrap())
+            .synchronize()
+            .build(),
+        );
+        worker
+    }
+
+    #[tokio::test]
+    async fn filters_see_rewritten_event() {
+        let bot = bot(40001).await;
+        let worker = worker(&bot);
+
+        let outcome = worker.handle(friend_message(&bot, 1, text("pong"))).await;
+        assert!(outcome.delivered);
+        assert_eq!(outcome.handled_by.as_deref(), Some("ping"));
+    }
+
+    #[tokio::test]
+    async fn dispatched_filters_see_rewritten_event() {
+        let bot = bot(40002).await;
+        let worker = worker(&bot);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        worker.add_middleware(1, Record(tx));
+
+        worker.dispatch(friend_message(&bot, 1, text("pong")));
+        assert!(worker.drain(Duration::from_secs(1)).await);
+        assert_eq!(rx.recv().await.unwrap().as_deref(), Some("ping"));
+    }
+
+    #[tokio::test]
+    async fn nested_handle_does_not_wait_for_ordered_listeners() {
+        let bot = bot(40003).await;
+        let worker: &'static ListenerWorker = Box::leak(Box::new(ListenerWorker::new()));
+        let inner = friend_message(&bot, 2, text("inner"));
+
+        // 两个顺序处理的监听器在处理同一事件时, 互相同步处理对方接收的事件
+        for name in ["a", "b"] {
+            let inner = inner.clone();
+            worker.register(
+                Listener::listening_on(move |e: FriendMessageEvent| {
+                    let inner = inner.clone();
+                    async move {
+                        if e.message().from_uin == 1 {
+                            worker.handle(inner).await;
+                        }
+                        true
+                    }
+                })
+                .with_name(name)
+                .synchronize()
+                .build(),
+            );
+        }
+
+        worker.dispatch(friend_message(&bot, 1, text("outer")));
+        assert!(worker.drain(Duration::from_secs(1)).await);
+    }
+
+    /// 记录事件最终由哪个监听器处理
+    struct Record(tokio::sync::mpsc::UnboundedSender<Option<String>>);
+
+    #[async_trait]
+    impl Middleware for Record {
+        async fn handle(&self, event: Event, next: Next<'_>) -> Outcome {
+            let outcome = next.run(event).await;
+            self.0.send(outcome.handled_by.clone()).ok();
+            outcome
+        }
+    }
 }
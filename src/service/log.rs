@@ -71,6 +71,17 @@ impl Write for LogWriter {
     }
 }
 
+/// 将日志写入磁盘
+pub fn flush_logs() {
+    let _ = io::stdout().flush();
+
+    if let Some(f) = LOG_FILE_OPENED.get() {
+        if let Err(e) = f.sync_all() {
+            eprintln!("Cannot flush log file: {}", e);
+        }
+    }
+}
+
 static LOG_PATH: &str = "log";
 
 pub fn log_dir_buf() -> PathBuf {
@@ -0,0 +1,407 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use rand::{thread_rng, RngCore};
+use ricq::{Client, LoginResponse, RQResult};
+use sha2::{Digest, Sha256};
+use toml_edit::{Decor, Document, Item, Table, TomlError, Value};
+use tracing::warn;
+
+use crate::config;
+use crate::config::login::BotConfig;
+
+/// 加密后的密码前缀
+pub const ENCRYPTED_PREFIX: &str = "enc:";
+/// 存放密钥的环境变量, 优先于密钥文件
+pub const KEY_ENV: &str = "ATRI_LOGIN_KEY";
+
+const NONCE_LEN: usize = 12;
+
+pub fn key_file_buf() -> PathBuf {
+    let mut p = config::service_config_dir_buf();
+    p.push("login.key");
+    p
+}
+
+#[derive(Debug)]
+pub enum CredentialError {
+    NoKey,
+    Io(io::Error),
+    Decode(base64::DecodeError),
+    Decrypt,
+    InvalidMd5,
+}
+
+impl Display for CredentialError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoKey => write!(
+                f,
+                "未找到密钥, 请设置环境变量{}或提供{}",
+                KEY_ENV,
+                key_file_buf().display()
+            ),
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Decode(e) => write!(f, "密码格式错误: {}", e),
+            Self::Decrypt => write!(f, "密码解密失败, 请检查密钥是否正确"),
+            Self::InvalidMd5 => write!(f, "password_md5必须为32位十六进制字符串"),
+        }
+    }
+}
+
+impl Error for CredentialError {}
+
+impl From<io::Error> for CredentialError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<base64::DecodeError> for CredentialError {
+    fn from(e: base64::DecodeError) -> Self {
+        Self::Decode(e)
+    }
+}
+
+pub enum Credential {
+    Password(String),
+    PasswordMd5([u8; 16]),
+}
+
+impl Credential {
+    pub async fn login(&self, client: &Client, account: i64) -> RQResult<LoginResponse> {
+        match self {
+            Self::Password(pwd) => client.password_login(account, pwd).await,
+            Self::PasswordMd5(md5) => client.password_md5_login(account, md5).await,
+        }
+    }
+}
+
+fn derive_key(secret: &[u8]) -> [u8; 32] {
+    Sha256::digest(secret).into()
+}
+
+/// 读取密钥, 环境变量优先
+pub fn load_key() -> Result<Option<[u8; 32]>, CredentialError> {
+    if let Ok(secret) = std::env::var(KEY_ENV) {
+        return Ok(Some(derive_key(secret.as_bytes())));
+    }
+
+    let path = key_file_buf();
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let secret = std::fs::read_to_string(path)?;
+    Ok(Some(derive_key(secret.trim().as_bytes())))
+}
+
+/// 读取密钥, 若不存在则生成新的密钥文件
+pub fn load_or_create_key() -> Result<[u8; 32], CredentialError> {
+    if let Some(key) = load_key()? {
+        return Ok(key);
+    }
+
+    let mut secret = [0u8; 32];
+    thread_rng().fill_bytes(&mut secret);
+    let secret = base64::encode(secret);
+
+    let path = key_file_buf();
+    write_secret(&path, &secret)?;
+    warn!(
+        "已生成密钥文件{}, 请妥善保管, 不要与登陆配置一同备份",
+        path.display()
+    );
+
+    Ok(derive_key(secret.as_bytes()))
+}
+
+/// 写入密钥, unix下创建文件时即设为仅所有者可读写
+fn write_secret(path: &Path, secret: &str) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)?.write_all(secret.as_bytes())
+}
+
+pub fn encrypt_password(key: &[u8; 32], password: &str) -> String {
+    let cipher = Aes256Gcm::new(key.into());
+
+    let mut nonce = [0u8; NONCE_LEN];
+    thread_rng().fill_bytes(&mut nonce);
+
+    let encrypted = cipher
+        .encrypt(Nonce::from_slice(&nonce), password.as_bytes())
+        .expect("Cannot encrypt password");
+
+    let mut data = nonce.to_vec();
+    data.extend(encrypted);
+
+    format!("{}{}", ENCRYPTED_PREFIX, base64::encode(data))
+}
+
+pub fn decrypt_password(key: &[u8; 32], encrypted: &str) -> Result<String, CredentialError> {
+    let encrypted = encrypted
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .unwrap_or(encrypted);
+    let data = base64::decode(encrypted)?;
+    if data.len() < NONCE_LEN {
+        return Err(CredentialError::Decrypt);
+    }
+
+    let (nonce, data) = data.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(key.into());
+    let decrypted = cipher
+        .decrypt(Nonce::from_slice(nonce), data)
+        .map_err(|_| CredentialError::Decrypt)?;
+
+    String::from_utf8(decrypted).map_err(|_| CredentialError::Decrypt)
+}
+
+pub fn parse_md5(s: &str) -> Result<[u8; 16], CredentialError> {
+    if s.len() != 32 || !s.is_ascii() {
+        return Err(CredentialError::InvalidMd5);
+    }
+
+    let mut md5 = [0u8; 16];
+    for (i, b) in md5.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16)
+            .map_err(|_| CredentialError::InvalidMd5)?;
+    }
+
+    Ok(md5)
+}
+
+pub fn md5_hex(password: &str) -> String {
+    format!("{:x}", md5::compute(password))
+}
+
+/// 获取Bot的登陆凭据, `password_md5`优先于`password`
+pub fn resolve_credential(bot: &BotConfig) -> Result<Option<Credential>, CredentialError> {
+    if let Some(ref md5) = bot.password_md5 {
+        return parse_md5(md5).map(|md5| Some(Credential::PasswordMd5(md5)));
+    }
+
+    match bot.password {
+        Some(ref pwd) if pwd.starts_with(ENCRYPTED_PREFIX) => {
+            let key = load_key()?.ok_or(CredentialError::NoKey)?;
+            decrypt_password(&key, pwd).map(|pwd| Some(Credential::Password(pwd)))
+        }
+        Some(ref pwd) => Ok(Some(Credential::Password(pwd.clone()))),
+        None => Ok(None),
+    }
+}
+
+/// 将配置中的明文密码替换为加密密码(或md5), 返回替换后的配置与替换的数量;
+/// 注释与格式保持不变
+pub fn encrypt_config_source(
+    source: &str,
+    key: Option<&[u8; 32]>,
+) -> Result<(String, usize), TomlError> {
+    let mut doc: Document = source.parse()?;
+    let mut count = 0;
+
+    let bots = match doc.get_mut("bot").and_then(Item::as_array_of_tables_mut) {
+        Some(bots) => bots,
+        None => return Ok((source.to_owned(), 0)),
+    };
+
+    for bot in bots.iter_mut() {
+        let password = match bot.get("password").and_then(Item::as_str) {
+            Some(pwd) if !pwd.starts_with(ENCRYPTED_PREFIX) => pwd.to_owned(),
+            _ => continue,
+        };
+
+        match key {
+            Some(key) => {
+                let old = bot
+                    .get_mut("password")
+                    .and_then(Item::as_value_mut)
+                    .expect("password is a string");
+                let mut new = Value::from(encrypt_password(key, &password));
+                *new.decor_mut() = old.decor().clone();
+                *old = new;
+            }
+            // password_md5优先于password, 已配置时只需移除明文密码
+            None if bot.contains_key("password_md5") => {
+                bot.remove("password");
+            }
+            None => replace_key(bot, "password", "password_md5", md5_hex(&password)),
+        }
+        count += 1;
+    }
+
+    Ok((doc.to_string(), count))
+}
+
+/// 替换表中的键, 保留其位置, 注释与值的格式
+fn replace_key(table: &mut Table, old: &str, new: &str, value: String) {
+    let keys: Vec<String> = table.iter().map(|(k, _)| k.to_owned()).collect();
+    let position = match keys.iter().position(|k| k == old) {
+        Some(p) => p,
+        None => return,
+    };
+
+    let key_decor = table.key_decor(old).cloned();
+    let mut value = Value::from(value);
+    if let Some(old) = table.get(old).and_then(Item::as_value) {
+        *value.decor_mut() = old.decor().clone();
+    }
+    table.remove(old);
+
+    // 之后的键需要重新插入, 使新键位于原来的位置
+    let rest: Vec<(String, Item, Option<Decor>)> = keys[position + 1..]
+        .iter()
+        .filter_map(|k| {
+            let decor = table.key_decor(k).cloned();
+            table.remove(k).map(|item| (k.clone(), item, decor))
+        })
+        .collect();
+
+    table.insert(new, Item::Value(value));
+    if let (Some(decor), Some(d)) = (key_decor, table.key_decor_mut(new)) {
+        *d = decor;
+    }
+
+    for (k, item, decor) in rest {
+        table.insert(&k, item);
+        if let (Some(decor), Some(d)) = (decor, table.key_decor_mut(&k)) {
+            *d = decor;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+
+    const SOURCE: &str = "\
+default_protocol = 'IPAD'
+
+[[bot]]
+account = 10001
+# Bot的密码
+password = 'secret' # 明文
+protocol = 'AndroidWatch'
+
+[[bot]]
+account = 10002
+password = 'other'
+password_md5 = 'e10adc3949ba59abbe56e057f20f883e'
+";
+
+    #[test]
+    fn password_round_trip() {
+        let encrypted = encrypt_password(&KEY, "密码 secret");
+        assert!(encrypted.starts_with(ENCRYPTED_PREFIX));
+        assert_ne!(encrypted, encrypt_password(&KEY, "密码 secret"));
+
+        assert_eq!(decrypt_password(&KEY, &encrypted).unwrap(), "密码 secret");
+        assert!(matches!(
+            decrypt_password(&[8; 32], &encrypted),
+            Err(CredentialError::Decrypt)
+        ));
+        assert!(matches!(
+            decrypt_password(&KEY, "enc:???"),
+            Err(CredentialError::Decode(_))
+        ));
+        assert!(matches!(
+            decrypt_password(&KEY, "enc:AAAA"),
+            Err(CredentialError::Decrypt)
+        ));
+    }
+
+    #[test]
+    fn md5_round_trip() {
+        let hex = md5_hex("123456");
+        assert_eq!(hex, "e10adc3949ba59abbe56e057f20f883e");
+        assert_eq!(parse_md5(&hex).unwrap(), md5::compute("123456").0);
+        assert_eq!(
+            parse_md5("E10ADC3949BA59ABBE56E057F20F883E").unwrap(),
+            md5::compute("123456").0
+        );
+
+        for invalid in [
+            "",
+            "e10adc",
+            "g10adc3949ba59abbe56e057f20f883e",
+            "密码密码密码密码密码密码密码密码密码密码1",
+        ] {
+            assert!(matches!(
+                parse_md5(invalid),
+                Err(CredentialError::InvalidMd5)
+            ));
+        }
+    }
+
+    #[test]
+    fn encrypt_source_keeps_comments() {
+        let (encrypted, count) = encrypt_config_source(SOURCE, Some(&KEY)).unwrap();
+        assert_eq!(count, 2);
+
+        let lines: Vec<&str> = encrypted.lines().collect();
+        assert_eq!(lines[4], "# Bot的密码");
+        assert!(lines[5].starts_with("password = \"enc:"), "{}", lines[5]);
+        assert!(lines[5].ends_with(" # 明文"), "{}", lines[5]);
+        assert_eq!(lines[6], "protocol = 'AndroidWatch'");
+
+        let value: toml::Value = toml::from_str(&encrypted).unwrap();
+        let bots = value["bot"].as_array().unwrap();
+        for (bot, pwd) in bots.iter().zip(["secret", "other"]) {
+            let encrypted = bot["password"].as_str().unwrap();
+            assert_eq!(decrypt_password(&KEY, encrypted).unwrap(), pwd);
+        }
+
+        // 已加密的密码不会再次加密
+        let (again, count) = encrypt_config_source(&encrypted, Some(&KEY)).unwrap();
+        assert_eq!(count, 0);
+        assert_eq!(again, encrypted);
+    }
+
+    #[test]
+    fn md5_source_replaces_password() {
+        let (replaced, count) = encrypt_config_source(SOURCE, None).unwrap();
+        assert_eq!(count, 2);
+
+        let lines: Vec<&str> = replaced.lines().collect();
+        assert_eq!(lines[4], "# Bot的密码");
+        assert_eq!(
+            lines[5],
+            format!("password_md5 = \"{}\" # 明文", md5_hex("secret"))
+        );
+        assert_eq!(lines[6], "protocol = 'AndroidWatch'");
+
+        let value: toml::Value = toml::from_str(&replaced).unwrap();
+        let bots = value["bot"].as_array().unwrap();
+        assert!(bots.iter().all(|bot| bot.get("password").is_none()));
+        // 已有password_md5时只移除明文密码
+        assert_eq!(
+            bots[1]["password_md5"].as_str(),
+            Some("e10adc3949ba59abbe56e057f20f883e")
+        );
+        assert_eq!(replaced.matches("password_md5").count(), 2);
+    }
+
+    #[test]
+    fn encrypt_source_without_bots() {
+        let source = "default_protocol = 'IPAD' # 协议\n";
+        assert_eq!(
+            encrypt_config_source(source, None).unwrap(),
+            (source.to_owned(), 0)
+        );
+        assert!(encrypt_config_source("[[bot]\n", None).is_err());
+    }
+}
@@ -0,0 +1,415 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rand::{thread_rng, Rng};
+use ricq::{LoginDeviceLocked, LoginNeedCaptcha, LoginResponse, RQError};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{error, info, warn};
+
+use crate::bot::proxy::Proxy;
+use crate::bot::status::BotState;
+use crate::bot::BotConfiguration;
+use crate::config::login::{BotConfig, LoginConfig, LoginMode, Protocol};
+use crate::service::login::credential::{resolve_credential, Credential};
+use crate::service::login::qrcode::qrcode_login;
+use crate::service::login::validate::{check_login_config, Severity};
+use crate::service::login::verify::{login_verifier, VerifyRequest};
+use crate::service::protocol::custom_version;
+use crate::{config, get_app, get_runtime, Bot};
+
+pub mod credential;
+pub mod qrcode;
+pub mod validate;
+pub mod verify;
+pub mod watcher;
+
+const MAX_VERIFY_TIMES: u8 = 3;
+
+pub fn login_config_path() -> PathBuf {
+    let mut p = config::service_config_dir_buf();
+    p.push("login.toml");
+    p
+}
+
+#[derive(Debug)]
+pub enum LoginConfigError {
+    Io(io::Error),
+    Parse {
+        error: toml::de::Error,
+        source_line: Option<String>,
+    },
+}
+
+impl Display for LoginConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse { error, source_line } => {
+                write!(f, "{}", error)?;
+                if let (Some((line, _)), Some(source)) = (error.line_col(), source_line) {
+                    write!(f, "\n{:>4} | {}", line + 1, source)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for LoginConfigError {}
+
+impl From<io::Error> for LoginConfigError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<LoginConfigError> for RQError {
+    fn from(e: LoginConfigError) -> Self {
+        match e {
+            LoginConfigError::Io(e) => RQError::IO(e),
+            or => RQError::Other(or.to_string()),
+        }
+    }
+}
+
+pub fn parse_login_config(s: &str) -> Result<LoginConfig, LoginConfigError> {
+    toml::from_str(s).map_err(|error| {
+        let source_line = error
+            .line_col()
+            .and_then(|(line, _)| s.lines().nth(line))
+            .map(String::from);
+
+        LoginConfigError::Parse { error, source_line }
+    })
+}
+
+/// 读取登陆配置, 若文件不存在则写入默认配置; 格式错误时不会修改文件
+pub async fn read_login_config() -> Result<LoginConfig, LoginConfigError> {
+    let login_conf_dir = config::service_config_dir_buf();
+    if !login_conf_dir.is_dir() {
+        fs::create_dir_all(&login_conf_dir).await?;
+    }
+    let path = login_config_path();
+
+    if !path.is_file() {
+        let mut f = fs::File::create(&path).await?;
+        f.write_all(config::login::DEFAULT_CONFIG).await?;
+
+        return Ok(LoginConfig::default());
+    }
+
+    let mut f = fs::File::open(&path).await?;
+    let mut s = String::new();
+    f.read_to_string(&mut s).await?;
+
+    parse_login_config(&s)
+}
+
+pub(crate) fn should_auto_login(bot: &BotConfig) -> bool {
+    if !bot.auto_login {
+        return false;
+    }
+
+    if bot.login_mode != LoginMode::QRCode {
+        let mut device = config::bots_dir_buf();
+        device.push(bot.account.to_string());
+        device.push("device.json");
+
+        if !device.is_file() {
+            warn!(
+                "未找到Bot({0})的设备信息, 跳过自动登陆; 可使用'device import {0} <file>'导入已有的设备文件, \
+                或设置login_mode = 'QRCode'扫码登陆",
+                bot.account
+            );
+            return false;
+        }
+    }
+
+    true
+}
+
+pub async fn login_bots() -> Result<(), RQError> {
+    match check_login_config().await {
+        Ok(diagnostics) => {
+            for diagnostic in diagnostics {
+                match diagnostic.severity {
+                    Severity::Warning => warn!("登陆配置: {}", diagnostic),
+                    Severity::Error => error!("登陆配置: {}", diagnostic),
+                }
+            }
+        }
+        Err(e) => error!("检查登陆配置文件失败: {}", e),
+    }
+
+    let login_conf = match read_login_config().await {
+        Ok(conf) => conf,
+        Err(e) => {
+            error!("读取登陆配置文件失败: {}", e);
+            LoginConfig::default()
+        }
+    };
+
+    let bots_path = config::bots_dir_buf();
+    if !bots_path.is_dir() {
+        fs::create_dir(&bots_path).await?;
+    }
+
+    get_runtime().spawn(watcher::watch_login_config(login_conf.clone()));
+
+    let mut logins = vec![];
+    for bot in login_conf.bots {
+        if !should_auto_login(&bot) {
+            continue;
+        }
+
+        let default_protocol = login_conf.default_protocol;
+        let handle =
+            get_runtime().spawn(async move { login_configured_bot(&bot, default_protocol).await });
+        logins.push(handle);
+
+        let random = { thread_rng().gen_range(0..44) as f32 / 11.2f32 };
+        tokio::time::sleep(Duration::from_secs_f32(random)).await;
+    }
+
+    for result in logins {
+        match result.await.expect("Login panics!") {
+            // todo: optimize
+            Ok(_) => {}
+            Err(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// 根据登陆配置文件中的配置登陆指定账号, 若配置中不存在该账号, 则仅尝试token登陆
+pub async fn login_account(account: i64) -> Result<Bot, RQError> {
+    let login_conf = read_login_config().await?;
+
+    let bot = login_conf
+        .bots
+        .into_iter()
+        .find(|b| b.account == account)
+        .unwrap_or(BotConfig {
+            account,
+            password: None,
+            password_md5: None,
+            protocol: None,
+            version: None,
+            auto_login: false,
+            login_mode: LoginMode::default(),
+            servers: vec![],
+            proxy: None,
+        });
+
+    login_configured_bot(&bot, login_conf.default_protocol).await
+}
+
+pub async fn logout_account(account: i64) -> Option<Bot> {
+    let bot = get_app().remove_bot(account)?;
+    bot.logout().await;
+    info!("{}已登出", bot);
+
+    Some(bot)
+}
+
+pub(crate) async fn login_configured_bot(
+    bot: &BotConfig,
+    default_protocol: Protocol,
+) -> Result<Bot, RQError> {
+    let account = bot.account;
+
+    let version = match bot.version {
+        Some(ref name) => custom_version(name).await.map_err(RQError::Other)?,
+        None => bot.protocol.unwrap_or(default_protocol).as_version(),
+    };
+
+    let proxy = match bot.proxy {
+        Some(ref proxy) => Some(
+            proxy
+                .parse::<Proxy>()
+                .map_err(|e| RQError::Other(e.to_string()))?,
+        ),
+        None => None,
+    };
+
+    match login_bot(
+        bot,
+        BotConfiguration {
+            work_dir: None,
+            version,
+            servers: bot.servers.clone(),
+            proxy,
+        },
+    )
+    .await
+    {
+        Ok(bot) => {
+            if let Err(e) = bot.refresh_group_list().await {
+                warn!("{}刷新群列表失败: {:?}", bot, e);
+            }
+            get_app().on_bot_online(&bot);
+            Ok(bot)
+        }
+        Err(e) => {
+            get_app().remove_bot(account);
+            Err(e)
+        }
+    }
+}
+
+pub async fn login_bot(bot_conf: &BotConfig, conf: BotConfiguration) -> Result<Bot, RQError> {
+    let account = bot_conf.account;
+    let bot = Bot::new(account, conf).await;
+    get_app().add_bot(bot.clone());
+    bot.set_state(BotState::Connecting);
+    bot.start().await?;
+
+    info!("Bot({})登陆中", account);
+    match bot.try_login().await {
+        Ok(_) => {
+            info!("{}登陆成功", bot);
+            Ok(bot)
+        }
+        Err(e) => {
+            //error!("Bot({})登陆失败: {:?}", account, e);
+            if let LoginMode::QRCode = bot_conf.login_mode {
+                info!("{}尝试扫码登陆", bot);
+                qrcode_login(&bot).await?;
+
+                info!("{}登陆成功", bot);
+                bot.save_token().await;
+                bot.on_login_success().await;
+
+                Ok(bot)
+            } else if let Some(credential) = password_credential(&bot, bot_conf) {
+                info!("{}尝试密码登陆", bot);
+                let mut resp = credential.login(bot.client(), account).await?;
+
+                let verifier = login_verifier();
+                let mut verify_times = 0;
+                let mut sms_requested = false;
+
+                loop {
+                    match resp {
+                        LoginResponse::DeviceLockLogin(..) => {
+                            let r = bot.client().device_lock_login().await?;
+                            resp = r;
+                        }
+                        LoginResponse::NeedCaptcha(LoginNeedCaptcha { ref verify_url, .. })
+                        | LoginResponse::DeviceLocked(LoginDeviceLocked {
+                            sms_phone: None,
+                            ref verify_url,
+                            ..
+                        }) if verify_times >= MAX_VERIFY_TIMES || verify_url.is_none() => {
+                            error!("{}登陆失败: 无法完成验证", bot);
+                            return Err(e);
+                        }
+                        LoginResponse::NeedCaptcha(LoginNeedCaptcha {
+                            verify_url: Some(ref url),
+                            ..
+                        }) => {
+                            verify_times += 1;
+                            let ticket = verifier.verify(&bot, VerifyRequest::Slider { url }).await;
+
+                            if let Some(ticket) = ticket {
+                                resp = bot.client().submit_ticket(&ticket).await?;
+                            } else {
+                                error!("{}登陆失败: 未完成滑块验证", bot);
+                                return Err(e);
+                            }
+                        }
+                        LoginResponse::DeviceLocked(LoginDeviceLocked {
+                            sms_phone: Some(ref phone),
+                            ref message,
+                            ..
+                        }) => {
+                            if verify_times >= MAX_VERIFY_TIMES {
+                                error!("{}登陆失败: 无法完成短信验证", bot);
+                                return Err(e);
+                            }
+
+                            if !sms_requested {
+                                warn!("{}设备锁: {}", bot, message.as_deref().unwrap_or(""));
+                                sms_requested = true;
+                                resp = bot.client().request_sms().await?;
+                                continue;
+                            }
+
+                            verify_times += 1;
+                            let code = verifier.verify(&bot, VerifyRequest::Sms { phone }).await;
+
+                            if let Some(code) = code {
+                                resp = bot.client().submit_sms_code(&code).await?;
+                            } else {
+                                error!("{}登陆失败: 未完成短信验证", bot);
+                                return Err(e);
+                            }
+                        }
+                        LoginResponse::DeviceLocked(LoginDeviceLocked {
+                            verify_url: Some(ref url),
+                            ref message,
+                            ..
+                        }) => {
+                            warn!("{}设备锁: {}", bot, message.as_deref().unwrap_or(""));
+                            verify_times += 1;
+
+                            if verifier
+                                .verify(&bot, VerifyRequest::DeviceLock { url })
+                                .await
+                                .is_some()
+                            {
+                                resp = credential.login(bot.client(), account).await?;
+                            } else {
+                                error!("{}登陆失败: 未完成设备锁验证", bot);
+                                return Err(e);
+                            }
+                        }
+                        LoginResponse::TooManySMSRequest => {
+                            error!("{}登陆失败: 短信验证码请求过于频繁", bot);
+                            return Err(e);
+                        }
+                        LoginResponse::Success(..) => {
+                            info!("{}登陆成功", bot);
+                            bot.save_token().await;
+                            bot.on_login_success().await;
+
+                            break;
+                        }
+                        LoginResponse::UnknownStatus(ref s) => {
+                            error!("{}登陆失败: {}", bot, s.message);
+                            return Err(e);
+                        }
+                        LoginResponse::AccountFrozen => {
+                            error!("{}登陆失败: 账号被冻结", bot);
+                            return Err(e);
+                        }
+                        _ => {
+                            error!("{}登陆失败: {:?}", bot, resp);
+                            return Err(e);
+                        }
+                    }
+                }
+
+                Ok(bot)
+            } else {
+                error!("{}登陆失败: {:?}", bot, e);
+                Err(e)
+            }
+        }
+    }
+}
+
+fn password_credential(bot: &Bot, bot_conf: &BotConfig) -> Option<Credential> {
+    match resolve_credential(bot_conf) {
+        Ok(credential) => credential,
+        Err(e) => {
+            error!("{}读取密码失败: {}", bot, e);
+            None
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use ricq::{LoginResponse, QRCodeConfirmed, QRCodeImageFetch, QRCodeState, RQError};
+use skia_safe::{AlphaType, CachingHint, ColorType, Data, Image, ImageInfo};
+use tokio::fs;
+use tracing::{error, info};
+
+use crate::Bot;
+
+pub async fn qrcode_login(bot: &Bot) -> Result<(), RQError> {
+    let client = bot.client();
+
+    let mut qrcode_path = bot.work_dir();
+    qrcode_path.push("qrcode.png");
+
+    let mut sig = Bytes::new();
+    let mut resp = client.fetch_qrcode().await?;
+
+    loop {
+        match resp {
+            QRCodeState::ImageFetch(QRCodeImageFetch {
+                ref image_data,
+                sig: ref image_sig,
+            }) => {
+                sig = image_sig.clone();
+
+                if let Err(e) = fs::write(&qrcode_path, image_data).await {
+                    error!("{}保存二维码失败: {}", bot, e);
+                }
+
+                match render_qrcode(image_data) {
+                    Some(qrcode) => info!(
+                        "{}请使用手机QQ扫描二维码登陆, 二维码图片已保存至{:?}\n{}",
+                        bot, qrcode_path, qrcode
+                    ),
+                    None => info!(
+                        "{}请使用手机QQ扫描二维码登陆, 二维码图片已保存至{:?}",
+                        bot, qrcode_path
+                    ),
+                }
+            }
+            QRCodeState::WaitingForScan => {}
+            QRCodeState::WaitingForConfirm => {
+                info!("{}扫码成功, 请在手机上确认登陆", bot);
+            }
+            QRCodeState::Timeout => {
+                info!("{}二维码已过期, 正在重新获取", bot);
+                resp = client.fetch_qrcode().await?;
+                continue;
+            }
+            QRCodeState::Confirmed(QRCodeConfirmed {
+                ref tmp_pwd,
+                ref tmp_no_pic_sig,
+                ref tgt_qr,
+                ..
+            }) => {
+                let _ = fs::remove_file(&qrcode_path).await;
+
+                let mut login = client.qrcode_login(tmp_pwd, tmp_no_pic_sig, tgt_qr).await?;
+                if let LoginResponse::DeviceLockLogin(..) = login {
+                    login = client.device_lock_login().await?;
+                }
+
+                if let LoginResponse::Success(..) = login {
+                    let uin = client.uin().await;
+                    if uin != bot.id() {
+                        error!("{}扫码登陆失败: 扫码账号({})与配置不一致", bot, uin);
+                        bot.logout().await;
+                        return Err(RQError::Other(String::from("扫码账号与配置不一致")));
+                    }
+
+                    return Ok(());
+                }
+
+                error!("{}扫码登陆失败: {:?}", bot, login);
+                return Err(RQError::Other(format!("{:?}", login)));
+            }
+            QRCodeState::Canceled => {
+                let _ = fs::remove_file(&qrcode_path).await;
+                error!("{}扫码登陆失败: 已在手机上取消登陆", bot);
+                return Err(RQError::Other(String::from("扫码登陆已取消")));
+            }
+        }
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        resp = client.query_qrcode_result(&sig).await?;
+    }
+}
+
+/// 将二维码图片转换为可在控制台显示的文本, 每个字符表示上下两个模块
+pub fn render_qrcode(png: &[u8]) -> Option<String> {
+    let image = Image::from_encoded(Data::new_copy(png))?;
+    let (w, h) = (image.width(), image.height());
+
+    let info = ImageInfo::new((w, h), ColorType::Gray8, AlphaType::Opaque, None);
+    let mut pixels = vec![0u8; (w * h) as usize];
+    if !image.read_pixels(&info, &mut pixels, w as usize, (0, 0), CachingHint::Allow) {
+        return None;
+    }
+
+    render_pixels(&pixels, w as usize, h as usize)
+}
+
+fn render_pixels(pixels: &[u8], w: usize, h: usize) -> Option<String> {
+    let dark = |x: usize, y: usize| pixels[y * w + x] < 128;
+
+    let (mut left, mut top, mut right, mut bottom) = (w, h, 0, 0);
+    for y in 0..h {
+        for x in 0..w {
+            if dark(x, y) {
+                left = left.min(x);
+                top = top.min(y);
+                right = right.max(x);
+                bottom = bottom.max(y);
+            }
+        }
+    }
+
+    if left > right || top > bottom {
+        return None;
+    }
+
+    // 左上角的定位图案宽7个模块
+    let finder = (left..=right).take_while(|x| dark(*x, top)).count();
+    let module = (finder as f32 / 7.0).max(1.0);
+    let size = ((right - left + 1) as f32 / module).round() as usize;
+
+    let is_dark = |mx: isize, my: isize| {
+        if mx < 0 || my < 0 || mx as usize >= size || my as usize >= size {
+            return false;
+        }
+
+        let x = left + ((mx as f32 + 0.5) * module) as usize;
+        let y = top + ((my as f32 + 0.5) * module) as usize;
+        x < w && y < h && dark(x, y)
+    };
+
+    // 浅色模块绘制为方块, 在深色背景的终端中也能扫描
+    const QUIET: isize = 2;
+    let mut s = String::new();
+    let mut my = -QUIET;
+    while my < size as isize + QUIET {
+        for mx in -QUIET..size as isize + QUIET {
+            s.push(match (is_dark(mx, my), is_dark(mx, my + 1)) {
+                (false, false) => '█',
+                (false, true) => '▀',
+                (true, false) => '▄',
+                (true, true) => ' ',
+            });
+        }
+        s.push('\n');
+        my += 2;
+    }
+
+    Some(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_pixels;
+
+    const SIZE: usize = 21;
+    const SCALE: usize = 4;
+    const BORDER: usize = 8;
+
+    fn dark_module(x: usize, y: usize) -> bool {
+        let finder = [(0, 0), (SIZE - 7, 0), (0, SIZE - 7)]
+            .into_iter()
+            .find(|&(fx, fy)| (fx..fx + 7).contains(&x) && (fy..fy + 7).contains(&y));
+
+        if let Some((fx, fy)) = finder {
+            let d = (x as isize - fx as isize - 3)
+                .abs()
+                .max((y as isize - fy as isize - 3).abs());
+            return d != 2;
+        }
+
+        if !(8..SIZE - 8).contains(&x) && y < 8 || x < 8 && y >= SIZE - 8 {
+            return false;
+        }
+
+        (x ^ y) & 3 == 1
+    }
+
+    fn bitmap() -> (Vec<u8>, usize) {
+        let w = SIZE * SCALE + BORDER * 2;
+        let mut pixels = vec![255u8; w * w];
+
+        for y in 0..SIZE * SCALE {
+            for x in 0..SIZE * SCALE {
+                if dark_module(x / SCALE, y / SCALE) {
+                    pixels[(y + BORDER) * w + x + BORDER] = 0;
+                }
+            }
+        }
+
+        (pixels, w)
+    }
+
+    #[test]
+    fn render_recovers_modules() {
+        let (pixels, w) = bitmap();
+        let s = render_pixels(&pixels, w, w).expect("no qrcode found");
+
+        let lines: Vec<Vec<char>> = s.lines().map(|l| l.chars().collect()).collect();
+        // 上下各留2个模块的空白, 每行字符表示2行模块
+        assert_eq!(lines.len(), 13);
+
+        let expected = |mx: isize, my: isize| {
+            mx >= 0
+                && my >= 0
+                && (mx as usize) < SIZE
+                && (my as usize) < SIZE
+                && dark_module(mx as usize, my as usize)
+        };
+
+        for (row, line) in lines.iter().enumerate() {
+            assert_eq!(line.len(), SIZE + 4);
+
+            let my = row as isize * 2 - 2;
+            for (col, c) in line.iter().enumerate() {
+                let mx = col as isize - 2;
+                let want = match (expected(mx, my), expected(mx, my + 1)) {
+                    (false, false) => '█',
+                    (false, true) => '▀',
+                    (true, false) => '▄',
+                    (true, true) => ' ',
+                };
+                assert_eq!(*c, want, "module ({}, {})", mx, my);
+            }
+        }
+    }
+
+    #[test]
+    fn render_blank_image() {
+        assert_eq!(render_pixels(&[255; 64], 8, 8), None);
+    }
+}
@@ -0,0 +1,683 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+use tokio::fs;
+
+use crate::bot::proxy::Proxy;
+use crate::config;
+use crate::config::login::{LoginConfig, LoginMode};
+use crate::service::login::credential::{
+    decrypt_password, load_key, parse_md5, CredentialError, ENCRYPTED_PREFIX,
+};
+use crate::service::login::login_config_path;
+use crate::service::protocol::{parse_apk_sign, parse_protocols_config, protocols_config_path};
+
+/// 示例配置中的账号与密码
+static PLACEHOLDER_ACCOUNTS: &[i64] = &[123456, 114514, 1919810];
+static PLACEHOLDER_PASSWORDS: &[&str] = &["123456", "114514", "1919810"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Warning => f.write_str("warning"),
+            Self::Error => f.write_str("error"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 从1开始的行号
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, line: Option<usize>, message: String) -> Self {
+        Self {
+            severity,
+            line,
+            message,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "[{}] line {}: {}", self.severity, line, self.message),
+            None => write!(f, "[{}] {}", self.severity, self.message),
+        }
+    }
+}
+
+/// 记录每个`[[bot]]`所在的行, 用于定位serde无法检查的诊断信息
+struct LineIndex<'a> {
+    lines: Vec<&'a str>,
+    /// 每个`[[bot]]`的起止行(不含结束行)
+    bots: Vec<(usize, usize)>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> Self {
+        let lines: Vec<&str> = source.lines().collect();
+        let mut bots = vec![];
+        let mut current = None::<usize>;
+
+        for (i, line) in lines.iter().enumerate() {
+            let line = line.trim();
+            if !line.starts_with('[') {
+                continue;
+            }
+
+            if let Some(start) = current.take() {
+                bots.push((start, i));
+            }
+
+            if array_table_name(line) == Some("bot") {
+                current = Some(i);
+            }
+        }
+
+        if let Some(start) = current {
+            bots.push((start, lines.len()));
+        }
+
+        Self { lines, bots }
+    }
+
+    fn find_key(&self, (start, end): (usize, usize), key: &str) -> Option<usize> {
+        self.lines[start..end]
+            .iter()
+            .position(|line| {
+                line.trim_start()
+                    .strip_prefix(key)
+                    .map(|rest| rest.trim_start().starts_with('='))
+                    .unwrap_or(false)
+            })
+            .map(|i| start + i + 1)
+    }
+
+    fn bot_key(&self, index: usize, key: &str) -> Option<usize> {
+        let range = *self.bots.get(index)?;
+        self.find_key(range, key).or(Some(range.0 + 1))
+    }
+}
+
+/// `[[name]]`中的表名, 不是数组表时返回`None`
+fn array_table_name(line: &str) -> Option<&str> {
+    let (name, _) = line.strip_prefix("[[")?.split_once("]]")?;
+    Some(name.trim())
+}
+
+/// 检查登陆配置时依赖的外部状态
+pub struct ValidateContext {
+    /// 所有自定义协议版本的名称, 为None时不检查version
+    pub version_names: Option<Vec<String>>,
+    pub key: Result<Option<[u8; 32]>, CredentialError>,
+    /// 存放各账号设备信息与token的目录
+    pub bots_dir: PathBuf,
+}
+
+/// 语法与字段类型由读取配置时使用的[`LoginConfig`]检查, 只报告第一个错误;
+/// 其余检查只针对serde无法表达的内容
+pub fn validate_login_config(source: &str, ctx: &ValidateContext) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    let conf: LoginConfig = match toml::from_str(source) {
+        Ok(conf) => conf,
+        Err(e) => {
+            diagnostics.push(serde_diagnostic(&e));
+            return diagnostics;
+        }
+    };
+
+    let index = LineIndex::new(source);
+
+    let mut seen = HashMap::<i64, usize>::new();
+    for (i, bot) in conf.bots.iter().enumerate() {
+        let line = |key: &str| index.bot_key(i, key);
+        let account = bot.account;
+
+        if let Some(first) = seen.insert(account, i) {
+            let first_line = index.bot_key(first, "account");
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                line("account"),
+                match first_line {
+                    Some(l) => format!("账号{}重复, 首次出现于第{}行", account, l),
+                    None => format!("账号{}重复", account),
+                },
+            ));
+        }
+
+        if PLACEHOLDER_ACCOUNTS.contains(&account) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                line("account"),
+                format!("账号{}为示例账号, 请修改为实际的账号", account),
+            ));
+        }
+
+        match bot.password {
+            Some(ref pwd) if pwd.starts_with(ENCRYPTED_PREFIX) => match &ctx.key {
+                Ok(Some(key)) => {
+                    if let Err(e) = decrypt_password(key, pwd) {
+                        diagnostics.push(Diagnostic::new(
+                            Severity::Error,
+                            line("password"),
+                            format!("账号{}的{}", account, e),
+                        ));
+                    }
+                }
+                Ok(None) => diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    line("password"),
+                    format!("账号{}的密码已加密, 但{}", account, CredentialError::NoKey),
+                )),
+                Err(e) => diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    line("password"),
+                    format!("读取密钥失败: {}", e),
+                )),
+            },
+            Some(ref pwd) if PLACEHOLDER_PASSWORDS.contains(&pwd.as_str()) => {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    line("password"),
+                    format!("账号{}的密码为示例密码", account),
+                ))
+            }
+            Some(_) => diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                line("password"),
+                format!("账号{}的密码为明文, 可使用'config encrypt'加密", account),
+            )),
+            None => {}
+        }
+
+        if let Some(ref md5) = bot.password_md5 {
+            if parse_md5(md5).is_err() {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    line("password_md5"),
+                    CredentialError::InvalidMd5.to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref name) = bot.version {
+            if matches!(ctx.version_names, Some(ref names) if !names.contains(name)) {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    line("version"),
+                    format!(
+                        "未找到自定义协议版本{}, 请检查{}",
+                        name,
+                        protocols_config_path().display()
+                    ),
+                ));
+            }
+        }
+
+        for server in bot.servers.iter().filter(|s| !is_valid_server(s)) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                line("servers"),
+                format!("服务器地址{}格式错误, 应为host:port或[ipv6]:port", server),
+            ));
+        }
+
+        if let Some(ref proxy) = bot.proxy {
+            if let Err(e) = proxy.parse::<Proxy>() {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    line("proxy"),
+                    e.to_string(),
+                ));
+            }
+        }
+
+        if bot.login_mode == LoginMode::QRCode {
+            continue;
+        }
+
+        let bot_dir = ctx.bots_dir.join(account.to_string());
+
+        if !bot_dir.join("device.json").is_file() {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                line("account"),
+                format!(
+                    "未找到账号{0}的device.json, 该账号不会自动登陆; \
+                    可使用'device import {0} <file>'导入已有的设备文件, 或设置login_mode = 'QRCode'扫码登陆",
+                    account
+                ),
+            ));
+        } else if !bot_dir.join("token.json").is_file()
+            && bot.password.is_none()
+            && bot.password_md5.is_none()
+        {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                line("account"),
+                format!("未找到账号{}的token.json且未配置密码, 将无法登陆", account),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// serde的错误信息包含位置, 位置已知时只保留错误本身, 行号单独显示
+fn serde_diagnostic(e: &toml::de::Error) -> Diagnostic {
+    let line = e.line_col().map(|(line, _)| line + 1);
+    let mut message = e.to_string();
+
+    if line.is_some() {
+        if let Some(pos) = message.rfind(" at line ") {
+            message.truncate(pos);
+        }
+    }
+
+    Diagnostic::new(Severity::Error, line, message)
+}
+
+fn is_valid_server(s: &str) -> bool {
+    match s.rsplit_once(':') {
+        Some((host, port)) => !host.is_empty() && port.parse::<u16>().is_ok(),
+        None => false,
+    }
+}
+
+/// 检查自定义协议配置, 返回所有版本的名称; 只读取文件, 不存在时视为没有自定义版本
+async fn validate_protocols(diagnostics: &mut Vec<Diagnostic>) -> Option<Vec<String>> {
+    let path = protocols_config_path();
+    if !path.is_file() {
+        return Some(vec![]);
+    }
+
+    let conf = match fs::read_to_string(&path)
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|s| parse_protocols_config(&s))
+    {
+        Ok(conf) => conf,
+        Err(e) => {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                None,
+                format!("读取{}失败: {}", path.display(), e),
+            ));
+            return None;
+        }
+    };
+
+    let mut names = Vec::<String>::new();
+    for version in conf.versions {
+        if names.contains(&version.name) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                None,
+                format!("{}: 协议版本{}重复", path.display(), version.name),
+            ));
+        }
+
+        if let Err(e) = parse_apk_sign(&version.apk_sign) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                None,
+                format!("{}: 协议版本{}的{}", path.display(), version.name, e),
+            ));
+        }
+
+        names.push(version.name);
+    }
+
+    Some(names)
+}
+
+/// 读取并检查登陆配置文件, 文件不存在时返回空
+pub async fn check_login_config() -> io::Result<Vec<Diagnostic>> {
+    let path = login_config_path();
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+
+    let source = fs::read_to_string(&path).await?;
+
+    let mut protocol_diagnostics = vec![];
+    let ctx = ValidateContext {
+        version_names: validate_protocols(&mut protocol_diagnostics).await,
+        key: load_key(),
+        bots_dir: config::bots_dir_buf(),
+    };
+
+    let mut diagnostics = validate_login_config(&source, &ctx);
+    diagnostics.extend(protocol_diagnostics);
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+
+    use super::{validate_login_config, Diagnostic, Severity, ValidateContext};
+    use crate::service::login::credential::{encrypt_password, CredentialError};
+
+    const KEY: [u8; 32] = [7; 32];
+
+    /// 10001: 有device.json与token.json; 10003: 只有device.json
+    fn bots_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("atri_validate_{}", std::process::id()));
+        for (account, files) in [
+            (10001, &["device.json", "token.json"][..]),
+            (10003, &["device.json"]),
+        ] {
+            let bot_dir = dir.join(account.to_string());
+            fs::create_dir_all(&bot_dir).unwrap();
+            for file in files {
+                fs::write(bot_dir.join(file), "{}").unwrap();
+            }
+        }
+        dir
+    }
+
+    fn ctx() -> ValidateContext {
+        ValidateContext {
+            version_names: Some(vec![String::from("Custom-1.0")]),
+            key: Ok(Some(KEY)),
+            bots_dir: bots_dir(),
+        }
+    }
+
+    fn validate(source: &str) -> Vec<Diagnostic> {
+        validate_login_config(source, &ctx())
+    }
+
+    fn bot(fields: &str) -> String {
+        format!(
+            "default_protocol = 'IPAD'\n\n[[bot]]\naccount = 10001\n{}\n",
+            fields
+        )
+    }
+
+    #[track_caller]
+    fn assert_single(
+        diagnostics: &[Diagnostic],
+        severity: Severity,
+        line: Option<usize>,
+        msg: &str,
+    ) {
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        let d = &diagnostics[0];
+        assert_eq!(d.severity, severity, "{}", d);
+        assert_eq!(d.line, line, "{}", d);
+        assert!(d.message.contains(msg), "'{}' not in '{}'", msg, d.message);
+    }
+
+    #[test]
+    fn valid_config() {
+        let pwd = encrypt_password(&KEY, "secret");
+        let source = bot(&format!(
+            "password = '{}'\nversion = 'Custom-1.0'\nservers = ['[::1]:8080', 'example.com:80']\n\
+            proxy = 'socks5://127.0.0.1:1080'\nlogin_mode = 'QRCode'",
+            pwd
+        ));
+        let diagnostics = validate(&source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        assert!(validate("default_protocol = 'MacOS'\nbot = []").is_empty());
+    }
+
+    #[test]
+    fn syntax_error() {
+        let diagnostics = validate("default_protocol = 'IPAD'\n[[bot]\naccount = 1");
+        assert_single(&diagnostics, Severity::Error, Some(2), "");
+    }
+
+    #[test]
+    fn default_protocol() {
+        // serde只能定位到所在的表
+        assert_single(
+            &validate("# comment\ndefault_protocol = 'Foo'\nbot = []"),
+            Severity::Error,
+            Some(1),
+            "unknown variant `Foo`",
+        );
+        assert_single(
+            &validate("[[bot]]\naccount = 10001"),
+            Severity::Error,
+            Some(1),
+            "missing field `default_protocol`",
+        );
+    }
+
+    #[test]
+    fn bot_not_array() {
+        let diagnostics = validate("default_protocol = 'IPAD'\nbot = 1");
+        assert_single(
+            &diagnostics,
+            Severity::Error,
+            Some(2),
+            "expected a sequence",
+        );
+        assert!(!diagnostics[0].message.contains("at line"));
+    }
+
+    #[test]
+    fn account() {
+        assert_single(
+            &validate("default_protocol = 'IPAD'\n\n[[bot]]\npassword_md5 = 'x'"),
+            Severity::Error,
+            Some(3),
+            "missing field `account`",
+        );
+        assert_single(
+            &validate("default_protocol = 'IPAD'\n[[bot]]\naccount = '10001'"),
+            Severity::Error,
+            Some(3),
+            "expected i64",
+        );
+
+        let duplicated = validate(
+            "default_protocol = 'IPAD'\n[[bot]]\naccount = 10001\n[[bot]]\naccount = 10001",
+        );
+        assert_single(&duplicated, Severity::Error, Some(5), "首次出现于第3行");
+    }
+
+    #[test]
+    fn other_tables_are_not_bots() {
+        let source = "default_protocol = 'IPAD'\n[[bot]]\naccount = 10001\n[[bot_extra]]\n\
+            account = 10001\n[[bots]]\naccount = 10001\n[[bot]]\naccount = 10001";
+        assert_single(
+            &validate(source),
+            Severity::Error,
+            Some(9),
+            "首次出现于第3行",
+        );
+    }
+
+    #[test]
+    fn placeholder() {
+        let diagnostics =
+            validate("default_protocol = 'IPAD'\n[[bot]]\naccount = 123456\nlogin_mode = 'QRCode'");
+        assert_single(&diagnostics, Severity::Warning, Some(3), "示例账号");
+
+        assert_single(
+            &validate(&bot("password = '1919810'")),
+            Severity::Warning,
+            Some(5),
+            "示例密码",
+        );
+    }
+
+    #[test]
+    fn password() {
+        assert_single(
+            &validate(&bot("password = 'secret'")),
+            Severity::Warning,
+            Some(5),
+            "明文",
+        );
+        assert_single(
+            &validate(&bot("password = 1919810")),
+            Severity::Error,
+            Some(5),
+            "expected a string for key `bot.password`",
+        );
+        assert_single(
+            &validate(&bot("password_md5 = 'abc'")),
+            Severity::Error,
+            Some(5),
+            &CredentialError::InvalidMd5.to_string(),
+        );
+        assert!(validate(&bot(&format!(
+            "password_md5 = '{:x}'",
+            md5::compute("secret")
+        )))
+        .is_empty());
+    }
+
+    #[test]
+    fn encrypted_password() {
+        let pwd = encrypt_password(&[8; 32], "secret");
+        let source = bot(&format!("password = '{}'", pwd));
+
+        assert_single(&validate(&source), Severity::Error, Some(5), "密码解密失败");
+
+        let no_key = ValidateContext {
+            key: Ok(None),
+            ..ctx()
+        };
+        assert_single(
+            &validate_login_config(&source, &no_key),
+            Severity::Error,
+            Some(5),
+            "密码已加密",
+        );
+
+        let bad_key = ValidateContext {
+            key: Err(CredentialError::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "denied",
+            ))),
+            ..ctx()
+        };
+        assert_single(
+            &validate_login_config(&source, &bad_key),
+            Severity::Error,
+            Some(5),
+            "读取密钥失败",
+        );
+    }
+
+    #[test]
+    fn protocol_and_version() {
+        assert_single(
+            &validate(&bot("protocol = 'Foo'")),
+            Severity::Error,
+            Some(3),
+            "unknown variant `Foo`",
+        );
+        assert_single(
+            &validate(&bot("version = 'Custom-2.0'")),
+            Severity::Error,
+            Some(5),
+            "未找到自定义协议版本Custom-2.0",
+        );
+        assert_single(
+            &validate(&bot("version = 1")),
+            Severity::Error,
+            Some(5),
+            "expected a string for key `bot.version`",
+        );
+
+        // 协议配置读取失败时不检查version
+        let unknown = ValidateContext {
+            version_names: None,
+            ..ctx()
+        };
+        assert!(validate_login_config(&bot("version = 'Custom-2.0'"), &unknown).is_empty());
+    }
+
+    #[test]
+    fn servers() {
+        assert_single(
+            &validate(&bot("servers = ['example.com', '1.1.1.1:80']")),
+            Severity::Error,
+            Some(5),
+            "服务器地址example.com格式错误",
+        );
+        assert_single(
+            &validate(&bot("servers = '1.1.1.1:80'")),
+            Severity::Error,
+            Some(5),
+            "expected a sequence for key `bot.servers`",
+        );
+    }
+
+    #[test]
+    fn proxy() {
+        assert_single(
+            &validate(&bot("proxy = 'ftp://127.0.0.1:21'")),
+            Severity::Error,
+            Some(5),
+            "",
+        );
+        assert_single(
+            &validate(&bot("proxy = 1080")),
+            Severity::Error,
+            Some(5),
+            "expected a string for key `bot.proxy`",
+        );
+    }
+
+    #[test]
+    fn login_mode() {
+        assert_single(
+            &validate(&bot("login_mode = 'Sms'")),
+            Severity::Error,
+            Some(3),
+            "unknown variant `Sms`",
+        );
+    }
+
+    #[test]
+    fn bot_files() {
+        let missing_device = "default_protocol = 'IPAD'\n[[bot]]\naccount = 10002";
+        assert_single(
+            &validate(missing_device),
+            Severity::Warning,
+            Some(3),
+            "未找到账号10002的device.json",
+        );
+        // 扫码登陆不需要device.json
+        assert!(validate(&format!("{}\nlogin_mode = 'QRCode'", missing_device)).is_empty());
+
+        let missing_token = "default_protocol = 'IPAD'\n[[bot]]\naccount = 10003";
+        assert_single(
+            &validate(missing_token),
+            Severity::Warning,
+            Some(3),
+            "未找到账号10003的token.json",
+        );
+        assert!(validate(&format!(
+            "{}\npassword_md5 = '{:x}'",
+            missing_token,
+            md5::compute("secret")
+        ))
+        .is_empty());
+    }
+}
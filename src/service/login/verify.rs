@@ -0,0 +1,73 @@
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tracing::{info, warn};
+
+use crate::service::command::request_input;
+use crate::Bot;
+
+pub enum VerifyRequest<'a> {
+    /// 滑块验证, 需要返回ticket
+    Slider { url: &'a str },
+    /// 短信验证, 需要返回短信验证码
+    Sms { phone: &'a str },
+    /// 设备锁验证, 使用手机QQ打开链接完成验证后返回任意值
+    DeviceLock { url: &'a str },
+}
+
+/// 密码登陆时的验证处理, 返回`None`表示放弃验证
+#[async_trait]
+pub trait LoginVerifier: Send + Sync + 'static {
+    async fn verify(&self, bot: &Bot, request: VerifyRequest<'_>) -> Option<String>;
+}
+
+static VERIFY_TIMEOUT: Duration = Duration::from_secs(300);
+
+pub struct ConsoleVerifier;
+
+#[async_trait]
+impl LoginVerifier for ConsoleVerifier {
+    async fn verify(&self, bot: &Bot, request: VerifyRequest<'_>) -> Option<String> {
+        match request {
+            VerifyRequest::Slider { url } => info!(
+                "{}需要滑块验证, 请在浏览器中打开以下链接完成验证, 然后输入获取到的ticket:\n{}",
+                bot, url
+            ),
+            VerifyRequest::Sms { phone } => {
+                info!("{}需要短信验证, 请输入发送至{}的验证码", bot, phone)
+            }
+            VerifyRequest::DeviceLock { url } => info!(
+                "{}需要设备锁验证, 请使用手机QQ打开以下链接完成验证, 完成后按回车继续:\n{}",
+                bot, url
+            ),
+        }
+
+        match tokio::time::timeout(VERIFY_TIMEOUT, request_input()).await {
+            Ok(Some(input)) => Some(input.trim().to_owned()),
+            Ok(None) => None,
+            Err(_) => {
+                warn!("{}等待验证输入超时", bot);
+                None
+            }
+        }
+    }
+}
+
+fn verifier_lock() -> &'static RwLock<Arc<dyn LoginVerifier>> {
+    static VERIFIER: OnceLock<RwLock<Arc<dyn LoginVerifier>>> = OnceLock::new();
+    VERIFIER.get_or_init(|| RwLock::new(Arc::new(ConsoleVerifier)))
+}
+
+pub fn login_verifier() -> Arc<dyn LoginVerifier> {
+    verifier_lock()
+        .read()
+        .expect("Cannot read login verifier")
+        .clone()
+}
+
+pub fn set_login_verifier<V: LoginVerifier>(verifier: V) {
+    *verifier_lock()
+        .write()
+        .expect("Cannot write login verifier") = Arc::new(verifier);
+}
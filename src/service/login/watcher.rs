@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use tokio::fs;
+use tracing::{error, info, warn};
+
+use crate::config::login::{BotConfig, LoginConfig, Protocol};
+use crate::service::command::exit_requested;
+use crate::service::login::{
+    login_config_path, login_configured_bot, logout_account, parse_login_config, should_auto_login,
+    LoginConfigError,
+};
+use crate::{get_app, get_runtime};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(3);
+
+fn modified_time() -> Option<SystemTime> {
+    login_config_path()
+        .metadata()
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// 与[`read_login_config`](super::read_login_config)不同, 文件不存在时不会写入默认配置
+async fn reload_login_config() -> Result<LoginConfig, LoginConfigError> {
+    let s = fs::read_to_string(login_config_path()).await?;
+    parse_login_config(&s)
+}
+
+/// 定时检查登陆配置文件, 修改后根据新旧配置的差异登陆, 登出或重新登陆Bot
+pub async fn watch_login_config(mut current: LoginConfig) {
+    let mut last_modified = modified_time();
+
+    while !exit_requested() {
+        tokio::time::sleep(CHECK_INTERVAL).await;
+
+        let modified = modified_time();
+        if modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+
+        if modified.is_none() {
+            warn!("登陆配置文件不存在, 保持原有配置");
+            continue;
+        }
+
+        let new = match reload_login_config().await {
+            Ok(conf) => conf,
+            Err(e) => {
+                error!("重新加载登陆配置文件失败, 保持原有配置: {}", e);
+                continue;
+            }
+        };
+
+        info!("登陆配置文件已修改, 重新加载");
+        apply_changes(&current, &new).await;
+        current = new;
+    }
+}
+
+/// 修改后需要重新登陆的配置
+fn connection_key<'a>(
+    bot: &'a BotConfig,
+    conf: &LoginConfig,
+) -> (
+    Protocol,
+    &'a Option<String>,
+    &'a [String],
+    &'a Option<String>,
+) {
+    (
+        bot.protocol.unwrap_or(conf.default_protocol),
+        &bot.version,
+        &bot.servers,
+        &bot.proxy,
+    )
+}
+
+async fn apply_changes(old: &LoginConfig, new: &LoginConfig) {
+    let old_bots: HashMap<i64, &BotConfig> = old.bots.iter().map(|b| (b.account, b)).collect();
+    let new_bots: HashMap<i64, &BotConfig> = new.bots.iter().map(|b| (b.account, b)).collect();
+
+    for account in old_bots.keys() {
+        if !new_bots.contains_key(account) && logout_account(*account).await.is_some() {
+            info!("Bot({})已从登陆配置中移除", account);
+        }
+    }
+
+    for (account, &bot) in &new_bots {
+        let running = get_app().bot(*account).filter(|b| b.is_online());
+
+        match old_bots.get(account) {
+            Some(old_bot) if running.is_some() => {
+                if connection_key(old_bot, old) == connection_key(bot, new) {
+                    continue;
+                }
+
+                info!("Bot({})的登陆协议或连接配置已修改, 重新登陆", account);
+                logout_account(*account).await;
+            }
+            Some(old_bot) if old_bot.auto_login || !should_auto_login(bot) => continue,
+            _ if running.is_some() || !should_auto_login(bot) => continue,
+            _ => {}
+        }
+
+        let bot = bot.clone();
+        let default_protocol = new.default_protocol;
+        get_runtime().spawn(async move {
+            if let Err(e) = login_configured_bot(&bot, default_protocol).await {
+                error!("Bot({})登陆失败: {:?}", bot.account, e);
+            }
+        });
+    }
+}
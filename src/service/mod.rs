@@ -3,17 +3,22 @@ use std::fs::File;
 
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
 use std::{fs, io, mem};
 
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
 pub mod command;
+pub mod http;
 pub mod listeners;
 pub mod log;
 pub mod login;
 pub mod plugin;
+pub mod protocol;
+pub mod routing;
+pub mod runtime;
+pub mod shutdown;
 
 fn get_service_path() -> &'static PathBuf {
     static PATH: OnceLock<PathBuf> = OnceLock::new();
@@ -24,10 +29,29 @@ fn get_service_path() -> &'static PathBuf {
     })
 }
 
+/// 已启动的服务, 关闭时按启动的相反顺序关闭; 不持有服务, 服务被丢弃时仍会立即关闭
+fn started_services() -> &'static Mutex<Vec<Weak<Service>>> {
+    static SERVICES: OnceLock<Mutex<Vec<Weak<Service>>>> = OnceLock::new();
+    SERVICES.get_or_init(Default::default)
+}
+
+/// 关闭所有仍存活的服务
+pub fn close_services() {
+    let services = mem::take(
+        &mut *started_services()
+            .lock()
+            .expect("Cannot lock started services"),
+    );
+
+    for service in services.iter().rev().filter_map(Weak::upgrade) {
+        service.close();
+    }
+}
+
 pub struct Service {
     name: String,
     path: PathBuf,
-    handler: Box<dyn ServiceHandler>,
+    handler: Mutex<Option<Box<dyn ServiceHandler>>>,
 }
 
 impl Service {
@@ -38,7 +62,7 @@ impl Service {
         Self {
             name,
             path: p,
-            handler: Box::new(()),
+            handler: Mutex::new(Some(Box::new(()))),
         }
     }
 
@@ -53,7 +77,7 @@ impl Service {
     pub fn with_handler<H: ServiceHandler>(&mut self, handler: H) -> &mut Self {
         let handler: Box<dyn ServiceHandler> = Box::new(handler);
 
-        self.handler = handler;
+        *self.handler.get_mut().expect("Cannot lock service handler") = Some(handler);
         self
     }
 
@@ -122,20 +146,43 @@ impl Service {
     pub fn start(self) -> Arc<Self> {
         info!("正在启动{}服务", self.name);
         let arc = Arc::new(self);
-        arc.handler.on_start(&arc);
+        if let Some(handler) = &*arc.handler.lock().expect("Cannot lock service handler") {
+            handler.on_start(&arc);
+        }
+
+        let mut services = started_services()
+            .lock()
+            .expect("Cannot lock started services");
+        services.retain(|s| s.strong_count() > 0);
+        services.push(Arc::downgrade(&arc));
+        drop(services);
+
         arc
     }
+
+    /// 调用服务的`on_close`, 仅会调用一次
+    fn close(&self) {
+        let handler = self
+            .handler
+            .lock()
+            .ok()
+            .and_then(|mut handler| handler.take());
+
+        if let Some(mut handler) = handler {
+            info!("正在关闭{}服务", self.name);
+            handler.on_close(self);
+        }
+    }
 }
 
 impl Drop for Service {
     fn drop(&mut self) {
-        let mut handler: Box<dyn ServiceHandler> = Box::new(());
-        mem::swap(&mut handler, &mut self.handler);
-        handler.on_close(self);
+        self.close();
     }
 }
 
-pub trait ServiceHandler: 'static {
+/// 关闭时可能在其他线程中调用`on_close`, 因此处理器需要实现`Send`
+pub trait ServiceHandler: Send + 'static {
     fn on_start(&self, service: &Arc<Service>);
 
     fn on_close(&mut self, service: &Service) {
@@ -6,39 +6,37 @@ use std::{fs, io};
 
 use dashmap::DashMap;
 use libloading::Library;
-use tokio::runtime;
-use tokio::runtime::Runtime;
 use tracing::{error, info, trace};
 
 use atri_ffi::ffi::AtriManager;
 use atri_ffi::plugin::PluginInstance;
 
 use crate::plugin::ffi::get_plugin_vtable;
+use crate::service::runtime::{get_runtime_of, AtriRuntime, RuntimeKind};
 
 static PLUGINS_PATH: &str = "plugins";
 
 pub struct PluginManager {
     plugins: DashMap<usize, Plugin>,
-    async_runtime: Runtime,
 }
 
 impl PluginManager {
     pub fn new() -> Self {
-        let async_runtime = runtime::Builder::new_multi_thread()
-            .worker_threads(12)
-            .thread_name("PluginRuntime")
-            .enable_all()
-            .build()
-            .unwrap();
-
         Self {
             plugins: DashMap::new(),
-            async_runtime,
         }
     }
 
-    pub fn async_runtime(&self) -> &Runtime {
-        &self.async_runtime
+    pub fn async_runtime(&self) -> &'static AtriRuntime {
+        get_runtime_of(RuntimeKind::Plugin)
+    }
+
+    /// 禁用所有插件, 返回被禁用的插件数量
+    pub fn disable_all(&self) -> usize {
+        self.plugins
+            .iter()
+            .filter(|plugin| plugin.disable())
+            .count()
     }
 }
 
@@ -108,6 +106,8 @@ impl super::super::Atri {
                         match result {
                             Ok(p) => {
                                 info!("插件({})加载成功", name);
+                                let id = self.plugin_manager.plugins.len();
+                                self.plugin_manager.plugins.insert(id, p);
                             }
                             Err(e) => {
                                 error!("插件: {} 加载失败: {}", name, e);
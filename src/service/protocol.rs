@@ -0,0 +1,201 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use ricq::version::Version;
+use tokio::fs;
+use tracing::{error, info, warn};
+
+use crate::config;
+use crate::config::login::Protocol;
+use crate::config::protocol::{ProtocolsConfig, VersionConfig};
+
+pub fn protocols_config_path() -> PathBuf {
+    let mut p = config::service_config_dir_buf();
+    p.push("protocols.toml");
+    p
+}
+
+/// 读取自定义协议配置, 若文件不存在则写入默认配置
+async fn read_protocols_source() -> io::Result<String> {
+    let path = protocols_config_path();
+
+    if !path.is_file() {
+        let _ = fs::create_dir_all(config::service_config_dir_buf()).await;
+        if let Err(e) = fs::write(&path, config::protocol::DEFAULT_CONFIG).await {
+            error!("写入默认协议配置文件失败: {}", e);
+        }
+    }
+
+    fs::read_to_string(&path).await
+}
+
+pub fn parse_protocols_config(s: &str) -> Result<ProtocolsConfig, String> {
+    toml::from_str(s).map_err(|e| e.to_string())
+}
+
+pub fn parse_apk_sign(s: &str) -> Result<Vec<u8>, String> {
+    let s: String = s
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ':')
+        .collect();
+
+    if s.len() % 2 != 0 || !s.is_ascii() {
+        return Err(format!("apk_sign格式错误: {}", s));
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("apk_sign格式错误: {}", s))
+}
+
+/// 相同内容只泄漏一次, 多次重新加载配置不会使内存持续增长
+fn intern_bytes(bytes: &[u8]) -> &'static [u8] {
+    static INTERNED: OnceLock<Mutex<HashSet<&'static [u8]>>> = OnceLock::new();
+    let mut interned = INTERNED
+        .get_or_init(Default::default)
+        .lock()
+        .expect("Cannot lock interned strings");
+
+    if let Some(b) = interned.get(bytes) {
+        return b;
+    }
+
+    let leaked: &'static [u8] = Box::leak(bytes.to_vec().into_boxed_slice());
+    interned.insert(leaked);
+    leaked
+}
+
+fn intern(s: &str) -> &'static str {
+    std::str::from_utf8(intern_bytes(s.as_bytes())).expect("Interned bytes is not utf8")
+}
+
+struct StaticVersion {
+    protocol: Protocol,
+    version: Version,
+}
+
+impl StaticVersion {
+    fn new(conf: &VersionConfig) -> Result<Self, String> {
+        let apk_sign = parse_apk_sign(&conf.apk_sign)?;
+
+        let version = Version {
+            apk_id: intern(&conf.apk_id),
+            app_id: conf.app_id,
+            sub_app_id: conf.sub_app_id.unwrap_or(conf.app_id),
+            sort_version_name: intern(&conf.sort_version_name),
+            build_ver: intern(&conf.build_ver),
+            build_time: conf.build_time,
+            apk_sign: intern_bytes(&apk_sign),
+            sdk_version: intern(&conf.sdk_version),
+            sso_version: conf.sso_version,
+            misc_bitmap: conf.misc_bitmap,
+            sub_sig_map: conf.sub_sig_map,
+            main_sig_map: conf.main_sig_map,
+            protocol: conf.protocol.as_rq_protocol(),
+        };
+
+        Ok(Self {
+            protocol: conf.protocol,
+            version,
+        })
+    }
+
+    fn to_version(&self) -> Version {
+        let v = &self.version;
+        Version {
+            apk_id: v.apk_id,
+            app_id: v.app_id,
+            sub_app_id: v.sub_app_id,
+            sort_version_name: v.sort_version_name,
+            build_ver: v.build_ver,
+            build_time: v.build_time,
+            apk_sign: v.apk_sign,
+            sdk_version: v.sdk_version,
+            sso_version: v.sso_version,
+            misc_bitmap: v.misc_bitmap,
+            sub_sig_map: v.sub_sig_map,
+            main_sig_map: v.main_sig_map,
+            protocol: self.protocol.as_rq_protocol(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct LoadedVersions {
+    modified: Option<SystemTime>,
+    versions: HashMap<String, StaticVersion>,
+}
+
+fn loaded_versions() -> &'static tokio::sync::Mutex<LoadedVersions> {
+    static VERSIONS: OnceLock<tokio::sync::Mutex<LoadedVersions>> = OnceLock::new();
+    VERSIONS.get_or_init(Default::default)
+}
+
+/// 加载所有自定义协议版本, 格式错误的版本会被跳过
+async fn load_versions() -> Result<HashMap<String, StaticVersion>, String> {
+    let s = read_protocols_source().await.map_err(|e| e.to_string())?;
+    let root: toml::Value = toml::from_str(&s).map_err(|e| e.to_string())?;
+
+    let entries = match root.get("version") {
+        Some(toml::Value::Array(entries)) => entries.clone(),
+        Some(_) => return Err("version必须为数组([[version]])".into()),
+        None => vec![],
+    };
+
+    let mut versions = HashMap::new();
+    for (i, entry) in entries.into_iter().enumerate() {
+        let conf: VersionConfig = match entry.try_into() {
+            Ok(conf) => conf,
+            Err(e) => {
+                error!("第{}个自定义协议版本格式错误, 已跳过: {}", i + 1, e);
+                continue;
+            }
+        };
+
+        match StaticVersion::new(&conf) {
+            Ok(version) => {
+                if versions.insert(conf.name.clone(), version).is_some() {
+                    warn!("自定义协议版本{}重复, 将使用最后一个", conf.name);
+                }
+            }
+            Err(e) => error!("自定义协议版本{}无效, 已跳过: {}", conf.name, e),
+        }
+    }
+
+    Ok(versions)
+}
+
+/// 获取自定义协议版本, 配置文件修改后会重新加载
+pub async fn custom_version(name: &str) -> Result<Version, String> {
+    let mut loaded = loaded_versions().lock().await;
+
+    let modified = fs::metadata(protocols_config_path())
+        .await
+        .and_then(|meta| meta.modified())
+        .ok();
+
+    if modified.is_none() || modified != loaded.modified {
+        let versions = load_versions().await?;
+
+        info!("已加载{}个自定义协议版本", versions.len());
+        loaded.versions = versions;
+        loaded.modified = modified;
+    }
+
+    loaded
+        .versions
+        .get(name)
+        .map(StaticVersion::to_version)
+        .ok_or_else(|| {
+            format!(
+                "未找到自定义协议版本{}, 请检查{}",
+                name,
+                protocols_config_path().display()
+            )
+        })
+}
@@ -0,0 +1,486 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use tokio::runtime::Handle;
+use tracing::{error, info};
+
+use crate::config;
+use crate::config::routing::{RoutingConfig, RoutingStrategy};
+
+/// 群消息路由策略, 决定一个群由哪个Bot负责回复
+pub trait GroupRouter: Send + Sync + 'static {
+    /// `candidates`为当前在线且位于该群中的Bot, 若由某个Bot收到消息触发, 则该Bot位于首位
+    fn route(
+        &self,
+        assignments: &GroupAssignments,
+        group_id: i64,
+        candidates: &[i64],
+    ) -> Option<i64>;
+
+    /// Bot上线时是否需要重新评估该Bot所在群的分配
+    fn reroute_on_online(&self) -> bool {
+        false
+    }
+}
+
+pub struct FirstSeen;
+
+impl GroupRouter for FirstSeen {
+    fn route(&self, _: &GroupAssignments, _: i64, candidates: &[i64]) -> Option<i64> {
+        candidates.first().copied()
+    }
+}
+
+pub struct FixedRouter {
+    routes: HashMap<i64, i64>,
+}
+
+impl GroupRouter for FixedRouter {
+    fn route(
+        &self,
+        assignments: &GroupAssignments,
+        group_id: i64,
+        candidates: &[i64],
+    ) -> Option<i64> {
+        match self.routes.get(&group_id) {
+            Some(bot) if candidates.contains(bot) => Some(*bot),
+            _ => FirstSeen.route(assignments, group_id, candidates),
+        }
+    }
+
+    fn reroute_on_online(&self) -> bool {
+        true
+    }
+}
+
+pub struct LeastLoaded;
+
+impl GroupRouter for LeastLoaded {
+    fn route(&self, assignments: &GroupAssignments, _: i64, candidates: &[i64]) -> Option<i64> {
+        candidates
+            .iter()
+            .copied()
+            .min_by_key(|bot| assignments.assigned_count(*bot))
+    }
+}
+
+pub struct PriorityRouter {
+    order: Vec<i64>,
+}
+
+impl GroupRouter for PriorityRouter {
+    fn route(
+        &self,
+        assignments: &GroupAssignments,
+        group_id: i64,
+        candidates: &[i64],
+    ) -> Option<i64> {
+        self.order
+            .iter()
+            .find(|bot| candidates.contains(bot))
+            .copied()
+            .or_else(|| FirstSeen.route(assignments, group_id, candidates))
+    }
+
+    fn reroute_on_online(&self) -> bool {
+        true
+    }
+}
+
+impl From<RoutingConfig> for Box<dyn GroupRouter> {
+    fn from(conf: RoutingConfig) -> Self {
+        match conf.strategy {
+            RoutingStrategy::FirstSeen => Box::new(FirstSeen),
+            RoutingStrategy::Fixed => Box::new(FixedRouter {
+                routes: conf.fixed.iter().map(|r| (r.group, r.bot)).collect(),
+            }),
+            RoutingStrategy::LeastLoaded => Box::new(LeastLoaded),
+            RoutingStrategy::Priority => Box::new(PriorityRouter {
+                order: conf.priority,
+            }),
+        }
+    }
+}
+
+fn routing_config_path() -> PathBuf {
+    let mut p = config::service_config_dir_buf();
+    p.push("routing.toml");
+    p
+}
+
+fn group_bot_path() -> PathBuf {
+    let mut p = config::service_config_dir_buf();
+    p.push("group_bot.json");
+    p
+}
+
+pub fn load_router() -> Box<dyn GroupRouter> {
+    let path = routing_config_path();
+
+    let conf = if path.is_file() {
+        match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| toml::from_str::<RoutingConfig>(&s).map_err(|e| e.to_string()))
+        {
+            Ok(conf) => conf,
+            Err(e) => {
+                error!("读取群路由配置文件失败: {}, 将使用FirstSeen策略", e);
+                RoutingConfig::default()
+            }
+        }
+    } else {
+        let _ = fs::create_dir_all(config::service_config_dir_buf());
+        if let Err(e) = fs::write(&path, config::routing::DEFAULT_CONFIG) {
+            error!("写入默认群路由配置文件失败: {}", e);
+        }
+        RoutingConfig::default()
+    };
+
+    info!("群路由策略: {:?}", conf.strategy);
+    conf.into()
+}
+
+/// 群与负责Bot的分配表, 变更后写入`group_bot.json`;
+/// 在运行时中变更时由阻塞线程写入, 等待写入期间的多次变更合并为一次
+pub struct GroupAssignments {
+    map: Arc<DashMap<i64, i64>>,
+    store: Arc<AssignmentStore>,
+}
+
+/// 分配表的写入状态
+struct AssignmentStore {
+    path: PathBuf,
+    /// 已有等待执行的写入
+    pending: AtomicBool,
+    /// 同一时间只有一个写入
+    writing: Mutex<()>,
+}
+
+impl AssignmentStore {
+    /// 写入未保存的变更, 正在写入时等待其完成
+    fn write_pending(&self, map: &DashMap<i64, i64>) {
+        let _writing = self.writing.lock().expect("Cannot lock group bot store");
+        // 之后的变更需要再次写入
+        if !self.pending.swap(false, Ordering::AcqRel) {
+            return;
+        }
+
+        let map: BTreeMap<i64, i64> = map.iter().map(|r| (*r.key(), *r.value())).collect();
+        let s = serde_json::to_string_pretty(&map).expect("Cannot serialize group bot map");
+
+        if let Err(e) = write_atomically(&self.path, s.as_bytes()) {
+            error!("保存群分配信息失败: {}", e);
+        }
+    }
+}
+
+/// 先写入临时文件再重命名, 写入中断时不会留下不完整的文件
+fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+impl GroupAssignments {
+    pub fn load() -> Self {
+        Self::load_from(group_bot_path())
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let map = if path.is_file() {
+            match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|s| {
+                    serde_json::from_str::<HashMap<i64, i64>>(&s).map_err(|e| e.to_string())
+                }) {
+                Ok(map) => map.into_iter().collect(),
+                Err(e) => {
+                    error!("读取群分配信息失败: {}", e);
+                    DashMap::new()
+                }
+            }
+        } else {
+            DashMap::new()
+        };
+
+        Self {
+            map: Arc::new(map),
+            store: Arc::new(AssignmentStore {
+                path,
+                pending: AtomicBool::new(false),
+                writing: Mutex::new(()),
+            }),
+        }
+    }
+
+    pub fn get(&self, group_id: i64) -> Option<i64> {
+        self.map.get(&group_id).map(|r| *r.value())
+    }
+
+    pub fn set(&self, group_id: i64, bot_id: i64) -> Option<i64> {
+        let old = self.map.insert(group_id, bot_id);
+        if old != Some(bot_id) {
+            self.save();
+        }
+        old
+    }
+
+    /// 当前分配仍为`expected`时改为由`bot_id`负责, 否则保留其他调用者的分配;
+    /// 返回最终负责该群的Bot
+    pub fn assign(&self, group_id: i64, expected: Option<i64>, bot_id: i64) -> i64 {
+        let (assigned, changed) = match self.map.entry(group_id) {
+            Entry::Occupied(mut e) => {
+                let current = *e.get();
+                if Some(current) == expected {
+                    e.insert(bot_id);
+                    (bot_id, current != bot_id)
+                } else {
+                    (current, false)
+                }
+            }
+            Entry::Vacant(e) => {
+                e.insert(bot_id);
+                (bot_id, true)
+            }
+        };
+
+        // 写入时需要遍历分配表, 必须在释放entry后进行
+        if changed {
+            self.save();
+        }
+        assigned
+    }
+
+    pub fn remove(&self, group_id: i64) -> Option<i64> {
+        let old = self.map.remove(&group_id).map(|(_, bot)| bot);
+        if old.is_some() {
+            self.save();
+        }
+        old
+    }
+
+    pub fn assigned_count(&self, bot_id: i64) -> usize {
+        self.map.iter().filter(|r| *r.value() == bot_id).count()
+    }
+
+    pub fn groups_of(&self, bot_id: i64) -> Vec<i64> {
+        self.map
+            .iter()
+            .filter(|r| *r.value() == bot_id)
+            .map(|r| *r.key())
+            .collect()
+    }
+
+    /// 立即写入未保存的变更, 关闭时调用
+    pub fn flush(&self) {
+        self.store.write_pending(&self.map);
+    }
+
+    fn save(&self) {
+        // 等待中的写入会包含此次变更
+        if self.store.pending.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        match Handle::try_current() {
+            Ok(handle) => {
+                let (map, store) = (self.map.clone(), self.store.clone());
+                handle.spawn_blocking(move || store.write_pending(&map));
+            }
+            Err(_) => self.store.write_pending(&self.map),
+        }
+    }
+}
+
+/// 按路由策略重新为群分配Bot, 没有候选Bot时移除该群的分配
+pub(crate) fn reroute(
+    router: &dyn GroupRouter,
+    assignments: &GroupAssignments,
+    group_id: i64,
+    candidates: &[i64],
+) {
+    let old = assignments.get(group_id);
+
+    match router.route(assignments, group_id, candidates) {
+        Some(id) if old != Some(id) => {
+            info!("群({})改由Bot({})负责", group_id, id);
+            assignments.set(group_id, id);
+        }
+        Some(_) => {}
+        None => {
+            assignments.remove(group_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn assignments(name: &str, init: &[(i64, i64)]) -> (GroupAssignments, PathBuf) {
+        let path = std::env::temp_dir().join(format!(
+            "atri_group_bot_{}_{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let assignments = GroupAssignments::load_from(path.clone());
+        for &(group, bot) in init {
+            assignments.map.insert(group, bot);
+        }
+
+        (assignments, path)
+    }
+
+    fn saved(path: &Path) -> HashMap<i64, i64> {
+        let s = fs::read_to_string(path).expect("group_bot.json not written");
+        serde_json::from_str(&s).unwrap()
+    }
+
+    #[test]
+    fn first_seen_picks_first_candidate() {
+        let (a, _) = assignments("first_seen", &[]);
+
+        assert_eq!(FirstSeen.route(&a, 1, &[20, 10]), Some(20));
+        assert_eq!(FirstSeen.route(&a, 1, &[]), None);
+    }
+
+    #[test]
+    fn fixed_prefers_configured_bot() {
+        let (a, _) = assignments("fixed", &[]);
+        let router = FixedRouter {
+            routes: [(1, 10)].into_iter().collect(),
+        };
+
+        assert_eq!(router.route(&a, 1, &[20, 10]), Some(10));
+        // 配置的Bot不在候选中时退回FirstSeen
+        assert_eq!(router.route(&a, 1, &[20, 30]), Some(20));
+        assert_eq!(router.route(&a, 2, &[30, 10]), Some(30));
+        assert!(router.reroute_on_online());
+    }
+
+    #[test]
+    fn least_loaded_picks_bot_with_fewest_groups() {
+        let (a, _) = assignments("least_loaded", &[(1, 10), (2, 10), (3, 20)]);
+
+        assert_eq!(LeastLoaded.route(&a, 4, &[10, 20]), Some(20));
+        assert_eq!(LeastLoaded.route(&a, 4, &[10, 30]), Some(30));
+        assert_eq!(LeastLoaded.route(&a, 4, &[]), None);
+    }
+
+    #[test]
+    fn priority_follows_configured_order() {
+        let (a, _) = assignments("priority", &[]);
+        let router = PriorityRouter {
+            order: vec![30, 20, 10],
+        };
+
+        assert_eq!(router.route(&a, 1, &[10, 20]), Some(20));
+        assert_eq!(router.route(&a, 1, &[10, 30]), Some(30));
+        // 候选均不在优先级列表中时退回FirstSeen
+        assert_eq!(router.route(&a, 1, &[40, 50]), Some(40));
+        assert!(router.reroute_on_online());
+    }
+
+    #[test]
+    fn routing_config_selects_strategy() {
+        let conf: RoutingConfig = toml::from_str(
+            r#"
+strategy = "Fixed"
+
+[[fixed]]
+group = 1
+bot = 10
+"#,
+        )
+        .unwrap();
+        let router: Box<dyn GroupRouter> = conf.into();
+        let (a, _) = assignments("config", &[]);
+
+        assert_eq!(router.route(&a, 1, &[20, 10]), Some(10));
+    }
+
+    #[test]
+    fn reroute_assigns_and_saves() {
+        let (a, path) = assignments("reroute_assign", &[(1, 10)]);
+
+        reroute(&FirstSeen, &a, 1, &[20]);
+
+        assert_eq!(a.get(1), Some(20));
+        assert_eq!(saved(&path), [(1, 20)].into_iter().collect());
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn reroute_without_candidate_drops_and_saves() {
+        let (a, path) = assignments("reroute_drop", &[(1, 10), (2, 20)]);
+
+        reroute(&FirstSeen, &a, 1, &[]);
+
+        assert_eq!(a.get(1), None);
+        assert_eq!(a.get(2), Some(20));
+        assert_eq!(saved(&path), [(2, 20)].into_iter().collect());
+
+        let reloaded = GroupAssignments::load_from(path.clone());
+        assert_eq!(reloaded.get(1), None);
+        assert_eq!(reloaded.get(2), Some(20));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn assign_keeps_concurrent_assignment() {
+        let (a, path) = assignments("assign", &[]);
+
+        assert_eq!(a.assign(1, None, 10), 10);
+        // 分配已被其他Bot修改, 不覆盖
+        assert_eq!(a.assign(1, None, 20), 10);
+        assert_eq!(a.assign(1, Some(30), 20), 10);
+        assert_eq!(a.assign(1, Some(10), 20), 20);
+        assert_eq!(saved(&path), [(1, 20)].into_iter().collect());
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn concurrent_assign_has_one_winner() {
+        let (a, path) = assignments("assign_race", &[]);
+
+        let assigned: Vec<i64> = std::thread::scope(|s| {
+            let handles: Vec<_> = (10..18)
+                .map(|bot| {
+                    let a = &a;
+                    s.spawn(move || a.assign(1, None, bot))
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert!(assigned.iter().all(|bot| Some(*bot) == a.get(1)));
+        let _ = fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn save_in_runtime_replaces_file() {
+        let (a, path) = assignments("save_async", &[]);
+
+        a.set(1, 10);
+        a.set(2, 20);
+        a.flush();
+
+        assert_eq!(saved(&path), [(1, 10), (2, 20)].into_iter().collect());
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        assert!(!Path::new(&tmp).exists());
+        let _ = fs::remove_file(path);
+    }
+}
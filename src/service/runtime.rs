@@ -0,0 +1,219 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use tokio::runtime;
+use tokio::runtime::{Handle, Runtime};
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::config;
+use crate::config::runtime::{PoolConfig, RuntimeConfig};
+
+pub fn runtime_config_path() -> PathBuf {
+    let mut p = config::service_config_dir_buf();
+    p.push("runtime.toml");
+    p
+}
+
+fn read_runtime_config() -> RuntimeConfig {
+    let path = runtime_config_path();
+
+    if !path.is_file() {
+        let _ = fs::create_dir_all(config::service_config_dir_buf());
+        if let Err(e) = fs::write(&path, config::runtime::DEFAULT_CONFIG) {
+            error!("写入默认运行时配置文件失败: {}", e);
+        }
+    }
+
+    match fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| toml::from_str::<RuntimeConfig>(&s).map_err(|e| e.to_string()))
+    {
+        Ok(conf) => conf,
+        Err(e) => {
+            error!("读取运行时配置文件失败: {}, 将使用默认配置", e);
+            toml::from_slice(config::runtime::DEFAULT_CONFIG)
+                .expect("Cannot parse default runtime config")
+        }
+    }
+}
+
+fn runtime_config() -> &'static RuntimeConfig {
+    static CONFIG: OnceLock<RuntimeConfig> = OnceLock::new();
+    CONFIG.get_or_init(read_runtime_config)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeKind {
+    Global,
+    Listener,
+    Plugin,
+}
+
+impl RuntimeKind {
+    pub const ALL: [RuntimeKind; 3] = [Self::Global, Self::Listener, Self::Plugin];
+
+    fn thread_name(&self) -> &'static str {
+        match self {
+            Self::Global => "GlobalRuntime",
+            Self::Listener => "Listeners",
+            Self::Plugin => "PluginRuntime",
+        }
+    }
+
+    fn pool_config(&self) -> PoolConfig {
+        let conf = runtime_config();
+        match self {
+            Self::Global => conf.global,
+            Self::Listener => conf.listener,
+            Self::Plugin => conf.plugin,
+        }
+    }
+}
+
+impl Display for RuntimeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Global => f.write_str("global"),
+            Self::Listener => f.write_str("listener"),
+            Self::Plugin => f.write_str("plugin"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Metrics {
+    spawned: AtomicU64,
+    active: AtomicUsize,
+    threads: AtomicUsize,
+}
+
+/// 运行时的统计信息
+#[derive(Debug, Clone, Copy)]
+pub struct RuntimeMetrics {
+    pub kind: RuntimeKind,
+    pub worker_threads: usize,
+    /// 当前存活的线程数, 包括阻塞线程
+    pub alive_threads: usize,
+    pub spawned_tasks: u64,
+    pub active_tasks: usize,
+}
+
+/// 带有统计信息的运行时, 只有通过[`AtriRuntime::spawn`]创建的任务会被计数,
+/// 直接使用`tokio::spawn`创建的任务不会计入
+pub struct AtriRuntime {
+    kind: RuntimeKind,
+    worker_threads: usize,
+    metrics: Arc<Metrics>,
+    runtime: Runtime,
+}
+
+impl AtriRuntime {
+    fn new(kind: RuntimeKind) -> Self {
+        let worker_threads = kind
+            .pool_config()
+            .worker_threads
+            .filter(|n| *n > 0)
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1)
+            });
+
+        let metrics = Arc::new(Metrics::default());
+        let (start, stop) = (metrics.clone(), metrics.clone());
+
+        let runtime = runtime::Builder::new_multi_thread()
+            .worker_threads(worker_threads)
+            .thread_name(kind.thread_name())
+            .on_thread_start(move || {
+                start.threads.fetch_add(1, Ordering::Relaxed);
+            })
+            .on_thread_stop(move || {
+                stop.threads.fetch_sub(1, Ordering::Relaxed);
+            })
+            .enable_all()
+            .build()
+            .unwrap();
+
+        info!("{}运行时已创建, 工作线程数: {}", kind, worker_threads);
+
+        Self {
+            kind,
+            worker_threads,
+            metrics,
+            runtime,
+        }
+    }
+
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        struct Active(Arc<Metrics>);
+
+        impl Drop for Active {
+            fn drop(&mut self) {
+                self.0.active.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        self.metrics.spawned.fetch_add(1, Ordering::Relaxed);
+        self.metrics.active.fetch_add(1, Ordering::Relaxed);
+        let active = Active(self.metrics.clone());
+
+        self.runtime.spawn(async move {
+            let _active = active;
+            future.await
+        })
+    }
+
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    pub fn handle(&self) -> &Handle {
+        self.runtime.handle()
+    }
+
+    pub fn metrics(&self) -> RuntimeMetrics {
+        RuntimeMetrics {
+            kind: self.kind,
+            worker_threads: self.worker_threads,
+            alive_threads: self.metrics.threads.load(Ordering::Relaxed),
+            spawned_tasks: self.metrics.spawned.load(Ordering::Relaxed),
+            active_tasks: self.metrics.active.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn runtime_cell(kind: RuntimeKind) -> &'static OnceLock<AtriRuntime> {
+    static GLOBAL: OnceLock<AtriRuntime> = OnceLock::new();
+    static LISTENER: OnceLock<AtriRuntime> = OnceLock::new();
+    static PLUGIN: OnceLock<AtriRuntime> = OnceLock::new();
+
+    match kind {
+        RuntimeKind::Global => &GLOBAL,
+        RuntimeKind::Listener => &LISTENER,
+        RuntimeKind::Plugin => &PLUGIN,
+    }
+}
+
+/// 获取指定的运行时, 首次调用时根据配置创建
+pub fn get_runtime_of(kind: RuntimeKind) -> &'static AtriRuntime {
+    runtime_cell(kind).get_or_init(|| AtriRuntime::new(kind))
+}
+
+/// 所有运行时的统计信息, 未创建的运行时不会列出
+pub fn runtime_metrics() -> Vec<RuntimeMetrics> {
+    RuntimeKind::ALL
+        .iter()
+        .filter_map(|kind| runtime_cell(*kind).get())
+        .map(AtriRuntime::metrics)
+        .collect()
+}
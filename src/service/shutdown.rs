@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use tracing::{error, info, warn};
+
+use crate::channel::stop_accepting_events;
+use crate::service::close_services;
+use crate::service::command::request_exit;
+use crate::service::listeners::get_global_worker;
+use crate::service::log::flush_logs;
+use crate::{get_app, Atri};
+
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 等待SIGINT或SIGTERM
+pub async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+                return;
+            }
+            Err(e) => error!("监听SIGTERM失败: {}", e),
+        }
+    }
+
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        error!("监听SIGINT失败: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
+
+/// 依次停止接收事件, 等待监听器处理完成, 禁用插件, 关闭服务, 登出Bot并写入群分配信息与日志
+pub async fn shutdown(atri: &Atri) {
+    info!("正在关闭");
+    request_exit();
+
+    stop_accepting_events();
+
+    if !get_global_worker().drain(DRAIN_TIMEOUT).await {
+        warn!(
+            "等待监听器超时, 仍有{}个事件未处理完成",
+            get_global_worker().in_flight()
+        );
+    }
+
+    let disabled = atri.plugin_manager().disable_all();
+    info!("已禁用{}个插件", disabled);
+
+    close_services();
+
+    // 不从App中移除Bot, 避免重新分配群并覆盖已保存的群分配信息
+    for bot in get_app().bots() {
+        if bot.is_online() {
+            bot.save_token().await;
+        }
+        bot.logout().await;
+        info!("{}已登出", bot);
+    }
+    get_app().save_group_assignments();
+
+    info!("已关闭");
+    flush_logs();
+}